@@ -0,0 +1,216 @@
+//! Derive macros for the [`singletons`] crate.
+//!
+//! These macros are re-exported by `singletons` when its `derive` feature is
+//! enabled, which is the recommended way to use them.
+//!
+//! [`singletons`]: https://docs.rs/singletons
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, spanned::Spanned, Data, DeriveInput, Error, Fields, GenericArgument,
+    Lifetime, PathArguments, Type,
+};
+
+/// Derives `FromSingletons` for a struct, along with an inherent
+/// `from_set()` method.
+///
+/// Each field is fetched from the set by its type:
+///
+/// - `&'a T` borrows the value of type `T`, which is required.
+/// - `T` clones the value of type `T`, which is required.
+/// - `Option<&'a T>` and `Option<T>` do the same, but are [`None`] if the
+///   value is absent instead of causing an error.
+///
+/// If any required values are absent, `from_set()` returns a
+/// `MissingTypes` error that lists all of them.
+///
+/// Fields cannot be `&mut T` or `Option<&mut T>`, since the set is only
+/// borrowed immutably:
+///
+/// ```compile_fail
+/// use singletons::FromSingletons;
+///
+/// #[derive(FromSingletons)]
+/// struct Deps<'a> {
+///     counter: &'a mut u32,
+/// }
+/// ```
+#[proc_macro_derive(FromSingletons)]
+pub fn derive_from_singletons(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// How a single field is fetched from the set.
+struct Field {
+    /// The name used for the field's local variable.
+    binding: syn::Ident,
+    /// The type of the value in the set.
+    ty: Type,
+    required: bool,
+    cloned: bool,
+}
+
+fn expand(input: DeriveInput) -> Result<TokenStream2, Error> {
+    let data = match &input.data {
+        Data::Struct(data) => data,
+        _ => {
+            return Err(Error::new(
+                Span::call_site(),
+                "FromSingletons can only be derived for structs",
+            ))
+        }
+    };
+
+    let lifetimes: Vec<&Lifetime> = input
+        .generics
+        .lifetimes()
+        .map(|def| &def.lifetime)
+        .collect();
+    if lifetimes.len() > 1 {
+        return Err(Error::new(
+            input.generics.span(),
+            "FromSingletons supports at most one lifetime parameter",
+        ));
+    }
+    let set_lifetime = lifetimes.first().map_or_else(
+        || Lifetime::new("'__set", Span::call_site()),
+        |lt| (*lt).clone(),
+    );
+
+    let fields: Vec<Field> = data
+        .fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| classify(format_ident!("__field{}", i), &field.ty))
+        .collect::<Result<_, _>>()?;
+
+    let fetches = fields.iter().map(|field| {
+        let Field { binding, ty, .. } = field;
+        quote! {
+            let #binding = set.try_get::<#ty>();
+        }
+    });
+
+    let required: Vec<&Field> = fields.iter().filter(|field| field.required).collect();
+    let required_bindings: Vec<&syn::Ident> = required.iter().map(|field| &field.binding).collect();
+    let required_types = required.iter().map(|field| &field.ty);
+
+    let values = fields.iter().map(|field| {
+        let binding = &field.binding;
+        match (field.required, field.cloned) {
+            (true, false) => quote!(#binding),
+            (true, true) => quote!(::core::clone::Clone::clone(#binding)),
+            (false, false) => quote!(#binding),
+            (false, true) => {
+                quote!(::core::option::Option::map(#binding, ::core::clone::Clone::clone))
+            }
+        }
+    });
+    let construct = match &data.fields {
+        Fields::Named(named) => {
+            let names = named.named.iter().map(|field| &field.ident);
+            quote!(Self { #(#names: #values),* })
+        }
+        Fields::Unnamed(_) => quote!(Self(#(#values),*)),
+        Fields::Unit => quote!(Self),
+    };
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    // Without a lifetime of its own, the struct can be built from a set of
+    // any lifetime, so the lifetime is introduced by the impl instead.
+    let (method_generics, trait_impl_generics) = if lifetimes.is_empty() {
+        let mut generics = input.generics.clone();
+        generics.params.insert(0, syn::parse_quote!(#set_lifetime));
+        let (trait_impl_generics, _, _) = generics.split_for_impl();
+        (quote!(<#set_lifetime>), quote!(#trait_impl_generics))
+    } else {
+        (quote!(), quote!(#impl_generics))
+    };
+
+    Ok(quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// Assembles this struct from the values in a `SingletonSet`,
+            /// listing every missing type if any required values are absent.
+            pub fn from_set #method_generics(
+                set: &#set_lifetime ::singletons::SingletonSet,
+            ) -> ::core::result::Result<Self, ::singletons::MissingTypes> {
+                <Self as ::singletons::FromSingletons<#set_lifetime>>::from_set(set)
+            }
+        }
+
+        impl #trait_impl_generics ::singletons::FromSingletons<#set_lifetime>
+            for #name #ty_generics #where_clause
+        {
+            #[allow(irrefutable_let_patterns, unused_mut, unused_variables)]
+            fn from_set(
+                set: &#set_lifetime ::singletons::SingletonSet,
+            ) -> ::core::result::Result<Self, ::singletons::MissingTypes> {
+                #(#fetches)*
+
+                if let (#(::core::option::Option::Some(#required_bindings),)*) = (#(#required_bindings,)*) {
+                    return ::core::result::Result::Ok(#construct);
+                }
+
+                let mut missing = ::std::vec::Vec::new();
+                #(
+                    if #required_bindings.is_none() {
+                        missing.push(::singletons::Type::of::<#required_types>());
+                    }
+                )*
+                ::core::result::Result::Err(::singletons::MissingTypes::new(missing))
+            }
+        }
+    })
+}
+
+/// Determines how a field of type `ty` is fetched from the set.
+fn classify(binding: syn::Ident, ty: &Type) -> Result<Field, Error> {
+    let (inner, required) = match option_inner(ty) {
+        Some(inner) => (inner, false),
+        None => (ty, true),
+    };
+    match inner {
+        Type::Reference(reference) if reference.mutability.is_some() => Err(Error::new_spanned(
+            reference,
+            "FromSingletons fields cannot be `&mut` references",
+        )),
+        Type::Reference(reference) => Ok(Field {
+            binding,
+            ty: (*reference.elem).clone(),
+            required,
+            cloned: false,
+        }),
+        _ => Ok(Field {
+            binding,
+            ty: inner.clone(),
+            required,
+            cloned: true,
+        }),
+    }
+}
+
+/// Returns `T` if `ty` is written as `Option<T>`.
+fn option_inner(ty: &Type) -> Option<&Type> {
+    let path = match ty {
+        Type::Path(path) if path.qself.is_none() => &path.path,
+        _ => return None,
+    };
+    let segment = path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) if args.args.len() == 1 => match &args.args[0] {
+            GenericArgument::Type(inner) => Some(inner),
+            _ => None,
+        },
+        _ => None,
+    }
+}
@@ -0,0 +1,172 @@
+//! A bump arena that backs the values of an arena-mode [`SingletonSet`].
+//!
+//! The arena only hands out memory. It never runs destructors; that remains
+//! the job of the [`Erased`] value placed in the memory, which the set drops
+//! before the arena is reset or dropped.
+//!
+//! [`SingletonSet`]: crate::SingletonSet
+//! [`Erased`]: crate::erased::Erased
+
+use std::{
+    alloc::{self, Layout},
+    fmt::{Debug, Formatter},
+    ptr::{self, NonNull},
+};
+
+/// The size of the first chunk allocated by an arena without a capacity.
+const DEFAULT_CHUNK_SIZE: usize = 4096;
+
+/// The alignment of every chunk, which keeps padding low for common types.
+const CHUNK_ALIGN: usize = 16;
+
+struct Chunk {
+    ptr: NonNull<u8>,
+    layout: Layout,
+}
+
+pub(crate) struct Arena {
+    chunks: Vec<Chunk>,
+    /// The index of the chunk currently being bumped.
+    current: usize,
+    /// The offset of the first free byte in the current chunk.
+    offset: usize,
+}
+
+impl Arena {
+    /// Creates an arena that allocates its first chunk with at least
+    /// `capacity` bytes.
+    pub(crate) fn with_capacity(capacity: usize) -> Self {
+        let mut arena = Arena {
+            chunks: Vec::new(),
+            current: 0,
+            offset: 0,
+        };
+        if capacity > 0 {
+            arena.push_chunk(capacity);
+        }
+        arena
+    }
+
+    /// Returns the total number of bytes owned by the arena.
+    pub(crate) fn capacity(&self) -> usize {
+        self.chunks.iter().map(|chunk| chunk.layout.size()).sum()
+    }
+
+    /// Moves `value` into the arena, returning a pointer to it.
+    ///
+    /// The arena never drops the value. The caller is responsible for
+    /// dropping or moving it out before the arena is reset or dropped.
+    pub(crate) fn alloc<T>(&mut self, value: T) -> NonNull<T> {
+        let layout = Layout::new::<T>();
+        let ptr = if layout.size() == 0 {
+            NonNull::dangling()
+        } else {
+            self.alloc_layout(layout).cast::<T>()
+        };
+        // SAFETY: the pointer is valid for writes and aligned for `T`.
+        unsafe { ptr::write(ptr.as_ptr(), value) };
+        ptr
+    }
+
+    /// Makes all of the arena's memory available again without releasing
+    /// it.
+    ///
+    /// Any pointers previously returned by [`.alloc()`] must no longer be
+    /// used.
+    ///
+    /// [`.alloc()`]: Self::alloc()
+    pub(crate) fn reset(&mut self) {
+        self.current = 0;
+        self.offset = 0;
+    }
+
+    fn alloc_layout(&mut self, layout: Layout) -> NonNull<u8> {
+        while let Some(chunk) = self.chunks.get(self.current) {
+            let start = chunk.ptr.as_ptr() as usize + self.offset;
+            let padding = start.wrapping_neg() & (layout.align() - 1);
+            let end = self.offset + padding + layout.size();
+            if end <= chunk.layout.size() {
+                // SAFETY: `end` is within the chunk, so the pointer is too.
+                let ptr = unsafe { chunk.ptr.as_ptr().add(self.offset + padding) };
+                self.offset = end;
+                return NonNull::new(ptr).expect("chunk pointers are non-null");
+            }
+            self.current += 1;
+            self.offset = 0;
+        }
+
+        let previous = self.chunks.last().map_or(0, |chunk| chunk.layout.size());
+        let size = (previous * 2)
+            .max(DEFAULT_CHUNK_SIZE)
+            .max(layout.size() + layout.align());
+        self.push_chunk(size);
+        self.alloc_layout(layout)
+    }
+
+    fn push_chunk(&mut self, size: usize) {
+        let layout = Layout::from_size_align(size, CHUNK_ALIGN).expect("arena chunk is too large");
+        // SAFETY: `size` is never zero.
+        let ptr = unsafe { alloc::alloc(layout) };
+        let ptr = NonNull::new(ptr).unwrap_or_else(|| alloc::handle_alloc_error(layout));
+        self.chunks.push(Chunk { ptr, layout });
+    }
+}
+
+impl Drop for Arena {
+    fn drop(&mut self) {
+        for chunk in &self.chunks {
+            // SAFETY: every chunk was allocated by `push_chunk` with this
+            // layout.
+            unsafe { alloc::dealloc(chunk.ptr.as_ptr(), chunk.layout) }
+        }
+    }
+}
+
+impl Debug for Arena {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Arena")
+            .field("chunks", &self.chunks.len())
+            .field("capacity", &self.capacity())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arena_allocations_are_aligned_and_distinct() {
+        #[repr(align(64))]
+        struct Aligned(u8);
+
+        let mut arena = Arena::with_capacity(0);
+        let a = arena.alloc(1u8);
+        let b = arena.alloc(Aligned(2));
+        let c = arena.alloc(3u64);
+
+        assert_eq!(b.as_ptr() as usize % 64, 0);
+        assert_eq!(c.as_ptr() as usize % 8, 0);
+        unsafe {
+            assert_eq!(*a.as_ptr(), 1);
+            assert_eq!((*b.as_ptr()).0, 2);
+            assert_eq!(*c.as_ptr(), 3);
+        }
+    }
+
+    #[test]
+    fn arena_grows_and_reuses_memory_after_reset() {
+        let mut arena = Arena::with_capacity(64);
+        for i in 0..100u64 {
+            arena.alloc([i; 4]);
+        }
+        let capacity = arena.capacity();
+        assert!(capacity >= 100 * 32);
+
+        arena.reset();
+        for i in 0..100u64 {
+            arena.alloc([i; 4]);
+        }
+        assert_eq!(arena.capacity(), capacity);
+    }
+}
@@ -0,0 +1,164 @@
+use std::fmt::{Display, Formatter};
+
+use crate::{SingletonSet, Type};
+
+/// A builder for a [`SingletonSet`] that rejects duplicate types.
+///
+/// [`SingletonSet::insert()`] silently replaces the previous value of a
+/// type, which can hide mistakes when a set is assembled from many places.
+/// The builder instead records every type that is added more than once, and
+/// [`.build()`] reports all of them at once.
+///
+/// # Example
+///
+/// ```
+/// use singletons::SingletonSetBuilder;
+///
+/// let set = SingletonSetBuilder::new()
+///     .with(42u32)
+///     .with("hello")
+///     .build()
+///     .unwrap();
+/// assert_eq!(set.get::<u32>(), &42);
+///
+/// let error = SingletonSetBuilder::new()
+///     .with(1u8)
+///     .with(2u8)
+///     .build()
+///     .unwrap_err();
+/// assert_eq!(error.to_string(), "duplicate types in SingletonSet: u8");
+/// ```
+///
+/// [`.build()`]: Self::build()
+#[derive(Debug, Default)]
+pub struct SingletonSetBuilder {
+    set: SingletonSet,
+    duplicates: Vec<Type>,
+}
+
+impl SingletonSetBuilder {
+    /// Creates a builder for an empty set.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a builder for a set backed by an arena, as described in
+    /// [`SingletonSet::with_arena()`].
+    #[must_use]
+    pub fn with_arena() -> Self {
+        SingletonSetBuilder {
+            set: SingletonSet::with_arena(),
+            duplicates: Vec::new(),
+        }
+    }
+
+    /// Adds a value to the set.
+    ///
+    /// If a value of the same type was already added, the type is recorded
+    /// as a duplicate and the first value is kept.
+    #[must_use]
+    pub fn with<T>(mut self, value: T) -> Self
+    where
+        T: 'static,
+    {
+        self.add(value);
+        self
+    }
+
+    /// Adds the default value of a type to the set.
+    ///
+    /// Duplicates are handled as in [`.with()`].
+    ///
+    /// [`.with()`]: Self::with()
+    #[must_use]
+    pub fn with_default<T>(self) -> Self
+    where
+        T: 'static + Default,
+    {
+        self.with(T::default())
+    }
+
+    /// Adds a value to the set through a mutable reference, for use in
+    /// loops and conditionals.
+    ///
+    /// Duplicates are handled as in [`.with()`].
+    ///
+    /// [`.with()`]: Self::with()
+    pub fn add<T>(&mut self, value: T) -> &mut Self
+    where
+        T: 'static,
+    {
+        if self.set.contains::<T>() {
+            let ty = Type::of::<T>();
+            if !self.duplicates.contains(&ty) {
+                self.duplicates.push(ty);
+            }
+        } else {
+            self.set.insert(value);
+        }
+        self
+    }
+
+    /// Returns the set, or the types that were added more than once.
+    pub fn build(self) -> Result<SingletonSet, DuplicateTypes> {
+        if self.duplicates.is_empty() {
+            Ok(self.set)
+        } else {
+            Err(DuplicateTypes(self.duplicates))
+        }
+    }
+}
+
+/// The error returned by [`SingletonSetBuilder::build()`] when one or more
+/// types were added more than once.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DuplicateTypes(Vec<Type>);
+
+impl DuplicateTypes {
+    /// Returns the duplicated types, in the order they were first
+    /// duplicated.
+    #[must_use]
+    pub fn types(&self) -> &[Type] {
+        &self.0
+    }
+}
+
+impl Display for DuplicateTypes {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("duplicate types in SingletonSet: ")?;
+        for (i, ty) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            f.write_str(ty.as_name())?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for DuplicateTypes {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builder_reports_every_duplicate_once() {
+        let mut builder = SingletonSetBuilder::new().with(1u8).with("first");
+        for i in 0..3u16 {
+            builder.add(i);
+        }
+        builder.add(2u8).add("second");
+
+        let error = builder.build().unwrap_err();
+        assert_eq!(
+            error.types(),
+            [Type::of::<u16>(), Type::of::<u8>(), Type::of::<&str>()]
+        );
+        assert_eq!(
+            error.to_string(),
+            "duplicate types in SingletonSet: u16, u8, &str"
+        );
+    }
+}
@@ -0,0 +1,206 @@
+use crate::{SingletonMap, Type};
+
+/// A single difference between two [`SingletonMap`]s.
+///
+/// Created by [`SingletonMap::diff()`].
+#[derive(Debug, PartialEq, Eq)]
+pub enum Diff<'a, V> {
+    /// The type key is only in the new map.
+    Added(&'a Type, &'a V),
+    /// The type key is only in the old map.
+    Removed(&'a Type, &'a V),
+    /// The type key is in both maps, with unequal values, old then new.
+    Changed(&'a Type, &'a V, &'a V),
+}
+
+impl<'a, V> Clone for Diff<'a, V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, V> Copy for Diff<'a, V> {}
+
+impl<'a, V> Diff<'a, V> {
+    /// Returns the type key that differs.
+    #[must_use]
+    pub fn key(&self) -> &'a Type {
+        match *self {
+            Diff::Added(key, _) | Diff::Removed(key, _) | Diff::Changed(key, _, _) => key,
+        }
+    }
+}
+
+impl<V: PartialEq> SingletonMap<V> {
+    /// Returns an iterator over the differences from this map to `new`.
+    ///
+    /// Removed and changed keys are visited first, in the order of this
+    /// map, followed by added keys in the order of `new`. Keys with equal
+    /// values in both maps are skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use singletons::{Diff, SingletonMap, Type};
+    ///
+    /// let mut old = SingletonMap::new();
+    /// old.insert::<u8>("kept");
+    /// old.insert::<u16>("before");
+    /// old.insert::<u32>("removed");
+    ///
+    /// let mut new = SingletonMap::new();
+    /// new.insert::<u8>("kept");
+    /// new.insert::<u16>("after");
+    /// new.insert::<u64>("added");
+    ///
+    /// let diffs: Vec<_> = old.diff(&new).collect();
+    /// assert_eq!(diffs, [
+    ///     Diff::Changed(&Type::of::<u16>(), &"before", &"after"),
+    ///     Diff::Removed(&Type::of::<u32>(), &"removed"),
+    ///     Diff::Added(&Type::of::<u64>(), &"added"),
+    /// ]);
+    ///
+    /// let mut patched = old.clone();
+    /// patched.apply_diff(old.diff(&new));
+    /// assert_eq!(patched, new);
+    /// ```
+    pub fn diff<'a>(&'a self, new: &'a Self) -> DiffIter<'a, V> {
+        DiffIter {
+            old: self,
+            new,
+            old_index: 0,
+            new_index: 0,
+        }
+    }
+}
+
+impl<V: Clone> SingletonMap<V> {
+    /// Applies differences, such as those from [`.diff()`], to this map.
+    ///
+    /// Added and changed keys are set to their new values, and removed keys
+    /// are removed. New keys are appended, while changed keys keep their
+    /// position.
+    ///
+    /// [`.diff()`]: Self::diff()
+    pub fn apply_diff<'a, I>(&mut self, diffs: I)
+    where
+        I: IntoIterator<Item = Diff<'a, V>>,
+        V: 'a,
+    {
+        for diff in diffs {
+            match diff {
+                Diff::Added(key, value) | Diff::Changed(key, _, value) => {
+                    self.insert_type(*key, value.clone());
+                }
+                Diff::Removed(key, _) => {
+                    self.remove_by_type(key);
+                }
+            }
+        }
+    }
+}
+
+/// An iterator over the differences between two [`SingletonMap`]s.
+///
+/// Created by [`SingletonMap::diff()`].
+#[derive(Debug)]
+pub struct DiffIter<'a, V> {
+    old: &'a SingletonMap<V>,
+    new: &'a SingletonMap<V>,
+    /// The position of the next pair of `old` to compare against `new`.
+    old_index: usize,
+    /// The position of the next pair of `new` to check for additions.
+    new_index: usize,
+}
+
+impl<'a, V> Clone for DiffIter<'a, V> {
+    fn clone(&self) -> Self {
+        DiffIter { ..*self }
+    }
+}
+
+impl<'a, V: PartialEq> Iterator for DiffIter<'a, V> {
+    type Item = Diff<'a, V>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((key, old)) = self.old.get_index(self.old_index) {
+            self.old_index += 1;
+            match self.new.get_by_type(key) {
+                None => return Some(Diff::Removed(key, old)),
+                Some(new) if new != old => return Some(Diff::Changed(key, old, new)),
+                Some(_) => {}
+            }
+        }
+        while let Some((key, new)) = self.new.get_index(self.new_index) {
+            self.new_index += 1;
+            if !self.old.contains_type(key) {
+                return Some(Diff::Added(key, new));
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = (self.old.len() - self.old_index) + (self.new.len() - self.new_index);
+        (0, Some(remaining))
+    }
+}
+
+impl<'a, V: PartialEq> std::iter::FusedIterator for DiffIter<'a, V> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_of_equal_maps_is_empty() {
+        let mut map = SingletonMap::new();
+        map.insert::<u8>(1);
+        let mut reordered = SingletonMap::new();
+        reordered.insert::<u16>(2);
+        reordered.insert::<u8>(1);
+        map.insert::<u16>(2);
+
+        assert_eq!(map.diff(&reordered).count(), 0);
+        assert_eq!(map.diff(&map).count(), 0);
+    }
+
+    #[test]
+    fn apply_diff_keeps_positions_of_changed_keys() {
+        let mut old = SingletonMap::new();
+        old.insert::<u8>(1);
+        old.insert::<u16>(2);
+        old.insert::<u32>(3);
+
+        let mut new = SingletonMap::new();
+        new.insert::<u64>(4);
+        new.insert::<u32>(30);
+        new.insert::<u8>(1);
+
+        let diffs: Vec<_> = old.diff(&new).collect();
+        assert_eq!(
+            diffs.iter().map(Diff::key).copied().collect::<Vec<_>>(),
+            [Type::of::<u16>(), Type::of::<u32>(), Type::of::<u64>()]
+        );
+
+        let mut patched = old.clone();
+        patched.apply_diff(diffs);
+        assert_eq!(patched, new);
+        assert_eq!(patched.values().copied().collect::<Vec<_>>(), [1, 30, 4]);
+    }
+
+    #[test]
+    fn diffs_are_copy_for_any_value() {
+        #[derive(Debug, PartialEq)]
+        struct Unique(u8);
+
+        let old = SingletonMap::new();
+        let mut new = SingletonMap::new();
+        new.insert::<u8>(Unique(1));
+
+        let diff = old.diff(&new).next().unwrap();
+        let copy = diff;
+        assert_eq!(diff, copy);
+        assert_eq!(copy, Diff::Added(&Type::of::<u8>(), &Unique(1)));
+    }
+}
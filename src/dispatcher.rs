@@ -0,0 +1,282 @@
+use std::{
+    any::{Any, TypeId},
+    error::Error,
+    fmt::{self, Debug, Display, Formatter},
+};
+
+use indexmap::IndexMap;
+
+use crate::Type;
+
+/// A type-erased handler, which downcasts its message before calling the
+/// typed handler it wraps.
+type Handler<R> = Box<dyn Fn(&dyn Any) -> R>;
+
+/// A table of handlers keyed by the type of message they accept.
+///
+/// Each handler is registered with [`.register()`] as a closure over a
+/// concrete message type, and [`.dispatch()`] calls the handler for the
+/// runtime type of a `&dyn Any`. The downcast happens once, inside the
+/// dispatcher, instead of in every handler.
+///
+/// Messages of a type without a handler go to the fallback handler, if one
+/// was set with [`.set_fallback()`], and are otherwise reported as
+/// [`Unhandled`].
+///
+/// # Example
+///
+/// ```
+/// use std::any::Any;
+/// use singletons::Dispatcher;
+///
+/// struct Ping;
+/// struct Say(&'static str);
+///
+/// let mut dispatcher = Dispatcher::new();
+/// dispatcher
+///     .register(|_: &Ping| "pong".to_string())
+///     .register(|say: &Say| say.0.to_uppercase());
+///
+/// let message: &dyn Any = &Say("hello");
+/// assert_eq!(dispatcher.dispatch(message).unwrap(), "HELLO");
+/// assert_eq!(dispatcher.dispatch_typed(&Ping).unwrap(), "pong");
+/// assert!(dispatcher.dispatch_typed(&42u8).is_err());
+///
+/// dispatcher.set_fallback(|_| "unknown".to_string());
+/// assert_eq!(dispatcher.dispatch_typed(&42u8).unwrap(), "unknown");
+/// ```
+///
+/// [`.register()`]: Self::register()
+/// [`.dispatch()`]: Self::dispatch()
+/// [`.set_fallback()`]: Self::set_fallback()
+pub struct Dispatcher<R> {
+    /// The handlers by the [`TypeId`] of their message type, so messages can
+    /// be looked up by the `TypeId` of a `&dyn Any`.
+    handlers: IndexMap<TypeId, (Type, Handler<R>)>,
+    fallback: Option<Handler<R>>,
+}
+
+impl<R> Dispatcher<R> {
+    /// Creates an empty `Dispatcher` without a fallback handler.
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        Dispatcher {
+            handlers: IndexMap::new(),
+            fallback: None,
+        }
+    }
+
+    /// Returns the number of registered handlers, not counting the fallback
+    /// handler.
+    #[inline]
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.handlers.len()
+    }
+
+    /// Returns true if no handlers are registered, not counting the fallback
+    /// handler.
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.handlers.is_empty()
+    }
+
+    /// Returns true if a handler is registered for messages of type `T`.
+    #[must_use]
+    pub fn contains<T>(&self) -> bool
+    where
+        T: 'static,
+    {
+        self.handlers.contains_key(&TypeId::of::<T>())
+    }
+
+    /// Registers the handler for messages of type `T`, replacing the
+    /// previous handler for that type, if any.
+    pub fn register<T>(&mut self, handler: impl Fn(&T) -> R + 'static) -> &mut Self
+    where
+        T: 'static,
+    {
+        let handler: Handler<R> = Box::new(move |message| match message.downcast_ref::<T>() {
+            Some(message) => handler(message),
+            None => unreachable!("messages are dispatched by their own type"),
+        });
+        self.handlers
+            .insert(TypeId::of::<T>(), (Type::of::<T>(), handler));
+        self
+    }
+
+    /// Removes the handler for messages of type `T`, returning true if it
+    /// existed.
+    pub fn unregister<T>(&mut self) -> bool
+    where
+        T: 'static,
+    {
+        self.handlers.shift_remove(&TypeId::of::<T>()).is_some()
+    }
+
+    /// Sets the handler for messages of every type without a registered
+    /// handler, replacing the previous fallback handler, if any.
+    pub fn set_fallback(&mut self, fallback: impl Fn(&dyn Any) -> R + 'static) -> &mut Self {
+        self.fallback = Some(Box::new(fallback));
+        self
+    }
+
+    /// Removes the fallback handler, returning true if it existed.
+    pub fn remove_fallback(&mut self) -> bool {
+        self.fallback.take().is_some()
+    }
+
+    /// Returns true if a fallback handler is set.
+    #[must_use]
+    pub fn has_fallback(&self) -> bool {
+        self.fallback.is_some()
+    }
+
+    /// Calls the handler for the runtime type of the message, or the
+    /// fallback handler if there is none.
+    ///
+    /// The runtime type is the type behind the `dyn Any`, so a boxed message
+    /// must be passed as `&*boxed` rather than `&boxed`, which would be a
+    /// message of type `Box<dyn Any>`.
+    pub fn dispatch(&self, message: &dyn Any) -> Result<R, Unhandled> {
+        let id = message.type_id();
+        match (
+            self.handlers.get(&id).map(|(_, handler)| handler),
+            &self.fallback,
+        ) {
+            (Some(handler), _) | (None, Some(handler)) => Ok(handler(message)),
+            (None, None) => Err(Unhandled { id, ty: None }),
+        }
+    }
+
+    /// Calls the handler for messages of type `T`, or the fallback handler
+    /// if there is none.
+    ///
+    /// Unlike [`.dispatch()`], an [`Unhandled`] error from this method
+    /// knows the name of the message type.
+    ///
+    /// [`.dispatch()`]: Self::dispatch()
+    pub fn dispatch_typed<T>(&self, message: &T) -> Result<R, Unhandled>
+    where
+        T: 'static,
+    {
+        self.dispatch(message).map_err(|_| Unhandled {
+            id: TypeId::of::<T>(),
+            ty: Some(Type::of::<T>()),
+        })
+    }
+
+    /// Returns an iterator that visits the message type of each handler in
+    /// registration order.
+    pub fn types(&self) -> impl ExactSizeIterator<Item = &Type> + DoubleEndedIterator + '_ {
+        self.handlers.values().map(|(ty, _)| ty)
+    }
+}
+
+impl<R> Default for Dispatcher<R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R> Debug for Dispatcher<R> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Dispatcher")
+            .field("types", &self.types().collect::<Vec<_>>())
+            .field("fallback", &self.fallback.is_some())
+            .finish()
+    }
+}
+
+/// The error returned when a [`Dispatcher`] has no handler for a message
+/// and no fallback handler.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Unhandled {
+    id: TypeId,
+    ty: Option<Type>,
+}
+
+impl Unhandled {
+    /// Returns the [`TypeId`] of the message.
+    #[must_use]
+    pub fn message_type_id(&self) -> TypeId {
+        self.id
+    }
+
+    /// Returns the [`Type`] of the message, if it is known.
+    ///
+    /// The type is only known for messages dispatched with
+    /// [`Dispatcher::dispatch_typed()`], since a `&dyn Any` does not carry
+    /// the name of its type.
+    #[must_use]
+    pub fn ty(&self) -> Option<&Type> {
+        self.ty.as_ref()
+    }
+}
+
+impl Display for Unhandled {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.ty {
+            Some(ty) => write!(f, "no handler for messages of type `{}`", ty),
+            None => write!(f, "no handler for messages of type {:?}", self.id),
+        }
+    }
+}
+
+impl Error for Unhandled {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Ping;
+    struct Add(u32, u32);
+
+    fn dispatcher() -> Dispatcher<u32> {
+        let mut dispatcher = Dispatcher::new();
+        dispatcher
+            .register(|_: &Ping| 0)
+            .register(|add: &Add| add.0 + add.1);
+        dispatcher
+    }
+
+    #[test]
+    fn dispatcher_calls_the_handler_for_the_runtime_type() {
+        let dispatcher = dispatcher();
+        let messages: Vec<Box<dyn Any>> = vec![Box::new(Add(1, 2)), Box::new(Ping)];
+        let results: Vec<_> = messages
+            .iter()
+            .map(|message| dispatcher.dispatch(&**message))
+            .collect();
+        assert_eq!(results, [Ok(3), Ok(0)]);
+
+        assert_eq!(dispatcher.dispatch_typed(&Add(2, 2)), Ok(4));
+        assert_eq!(
+            dispatcher.types().copied().collect::<Vec<_>>(),
+            [Type::of::<Ping>(), Type::of::<Add>()]
+        );
+    }
+
+    #[test]
+    fn dispatcher_reports_unhandled_messages() {
+        let mut dispatcher = dispatcher();
+        assert!(dispatcher.unregister::<Ping>());
+        assert!(!dispatcher.contains::<Ping>());
+
+        let error = dispatcher.dispatch(&Ping).unwrap_err();
+        assert_eq!(error.message_type_id(), TypeId::of::<Ping>());
+        assert_eq!(error.ty(), None);
+
+        let error = dispatcher.dispatch_typed(&Ping).unwrap_err();
+        assert_eq!(error.ty(), Some(&Type::of::<Ping>()));
+        assert!(error.to_string().ends_with("Ping`"));
+
+        dispatcher.set_fallback(|_| 99);
+        assert_eq!(dispatcher.dispatch(&Ping), Ok(99));
+        assert_eq!(dispatcher.dispatch_typed(&Add(1, 1)), Ok(2));
+        assert!(dispatcher.remove_fallback());
+        assert!(dispatcher.dispatch(&Ping).is_err());
+    }
+}
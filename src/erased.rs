@@ -0,0 +1,291 @@
+//! Type-erased storage for the values held in a [`SingletonSet`] or
+//! [`Resources`].
+//!
+//! Values that are small enough are stored inline in the slot itself, so
+//! that a set of counters or flags does not perform one heap allocation per
+//! value. Everything else is boxed, or placed in the set's [`Arena`] if it
+//! has one.
+//!
+//! Inline values do not have a stable address. They move whenever the
+//! underlying `IndexMap` reallocates or reorders its entries, while boxed and
+//! arena values stay put. Every reference handed out by the set borrows the
+//! set itself, so safe code cannot observe the move, but a raw pointer to a
+//! small value is invalidated by any mutation of the set.
+//!
+//! The inline buffer is wrapped in an [`UnsafeCell`], because values with
+//! interior mutability, such as a `Cell` or an atomic, are mutated through
+//! the shared references returned by [`Erased::downcast_ref()`].
+//!
+//! [`SingletonSet`]: crate::SingletonSet
+//! [`Resources`]: crate::Resources
+//! [`Arena`]: crate::arena::Arena
+
+use std::{
+    any::{Any, TypeId},
+    cell::UnsafeCell,
+    fmt::{Debug, Formatter},
+    mem::{self, ManuallyDrop, MaybeUninit},
+    ptr::{self, NonNull},
+};
+
+use crate::arena::Arena;
+
+/// The number of machine words available for inline storage.
+const INLINE_WORDS: usize = 3;
+
+type InlineBuf = [MaybeUninit<usize>; INLINE_WORDS];
+
+/// Returns true if values of type `T` are stored inline instead of boxed.
+#[inline]
+pub(crate) const fn fits_inline<T>() -> bool {
+    mem::size_of::<T>() <= mem::size_of::<InlineBuf>()
+        && mem::align_of::<T>() <= mem::align_of::<InlineBuf>()
+}
+
+/// The operations needed to manage an unboxed value without knowing its
+/// type.
+struct Meta {
+    type_id: fn() -> TypeId,
+    drop: unsafe fn(*mut u8),
+}
+
+impl Meta {
+    fn of<T: 'static>() -> &'static Meta {
+        trait HasMeta {
+            const META: Meta;
+        }
+
+        impl<T: 'static> HasMeta for T {
+            const META: Meta = Meta {
+                type_id: TypeId::of::<T>,
+                drop: drop_in_place::<T>,
+            };
+        }
+
+        &<T as HasMeta>::META
+    }
+}
+
+unsafe fn drop_in_place<T>(ptr: *mut u8) {
+    ptr::drop_in_place(ptr.cast::<T>())
+}
+
+enum Repr {
+    Inline(UnsafeCell<InlineBuf>, &'static Meta),
+    Boxed(Box<dyn Any>),
+    /// A value in memory owned by an [`Arena`], which must outlive it.
+    Arena(NonNull<u8>, &'static Meta),
+}
+
+/// A single type-erased value.
+///
+/// The auto traits of `Erased` follow `Box<dyn Any>`, so it is neither
+/// `Send` nor `Sync` regardless of how the value is stored.
+pub(crate) struct Erased(Repr);
+
+impl Erased {
+    /// Erases the type of `value`, storing it inline if it fits.
+    pub(crate) fn new<T: 'static>(value: T) -> Self {
+        if fits_inline::<T>() {
+            let mut buf: InlineBuf = [MaybeUninit::uninit(); INLINE_WORDS];
+            // SAFETY: `fits_inline` guarantees the buffer is large enough
+            // and sufficiently aligned for `T`.
+            unsafe { ptr::write(buf.as_mut_ptr().cast::<T>(), value) };
+            Erased(Repr::Inline(UnsafeCell::new(buf), Meta::of::<T>()))
+        } else {
+            Erased(Repr::Boxed(Box::new(value)))
+        }
+    }
+
+    /// Erases the type of `value`, storing it inline if it fits and in
+    /// `arena` otherwise.
+    ///
+    /// The returned value must be dropped or downcast before `arena` is
+    /// reset or dropped.
+    pub(crate) fn new_in<T: 'static>(value: T, arena: &mut Arena) -> Self {
+        if fits_inline::<T>() {
+            Erased::new(value)
+        } else {
+            Erased(Repr::Arena(arena.alloc(value).cast(), Meta::of::<T>()))
+        }
+    }
+
+    /// Returns the [`TypeId`] of the stored value.
+    pub(crate) fn type_id(&self) -> TypeId {
+        match &self.0 {
+            Repr::Inline(_, meta) | Repr::Arena(_, meta) => (meta.type_id)(),
+            Repr::Boxed(boxed) => (**boxed).type_id(),
+        }
+    }
+
+    /// Returns true if the stored value is of type `T`.
+    #[inline]
+    pub(crate) fn is<T: 'static>(&self) -> bool {
+        self.type_id() == TypeId::of::<T>()
+    }
+
+    /// Returns true if the stored value lives inline in the slot.
+    #[cfg(test)]
+    pub(crate) fn is_inline(&self) -> bool {
+        matches!(self.0, Repr::Inline(..))
+    }
+
+    /// Returns a reference to the value if it is of type `T`.
+    pub(crate) fn downcast_ref<T: 'static>(&self) -> Option<&T> {
+        match &self.0 {
+            Repr::Inline(buf, _) if self.is::<T>() => {
+                // SAFETY: the type was checked above, and the buffer was
+                // initialized with a `T` in `Erased::new`. The pointer comes
+                // from the `UnsafeCell`, so `T` may have interior mutability.
+                Some(unsafe { &*buf.get().cast::<T>() })
+            }
+            Repr::Inline(..) => None,
+            Repr::Boxed(boxed) => boxed.downcast_ref(),
+            // SAFETY: the type was checked, and the arena outlives `self`.
+            Repr::Arena(ptr, _) if self.is::<T>() => Some(unsafe { ptr.cast::<T>().as_ref() }),
+            Repr::Arena(..) => None,
+        }
+    }
+
+    /// Returns a mutable reference to the value if it is of type `T`.
+    pub(crate) fn downcast_mut<T: 'static>(&mut self) -> Option<&mut T> {
+        let is_t = self.is::<T>();
+        match &mut self.0 {
+            Repr::Inline(buf, _) if is_t => {
+                // SAFETY: see `downcast_ref`.
+                Some(unsafe { &mut *buf.get_mut().as_mut_ptr().cast::<T>() })
+            }
+            Repr::Inline(..) => None,
+            Repr::Boxed(boxed) => boxed.downcast_mut(),
+            // SAFETY: see `downcast_ref`.
+            Repr::Arena(ptr, _) if is_t => Some(unsafe { ptr.cast::<T>().as_mut() }),
+            Repr::Arena(..) => None,
+        }
+    }
+
+    /// Moves the value out if it is of type `T`, giving back `self`
+    /// otherwise.
+    pub(crate) fn downcast<T: 'static>(self) -> Result<T, Self> {
+        if !self.is::<T>() {
+            return Err(self);
+        }
+
+        // The value is moved out below, so the destructor must not run.
+        let this = ManuallyDrop::new(self);
+        // SAFETY: `this` is never used or dropped again after being read.
+        match unsafe { ptr::read(&this.0) } {
+            // SAFETY: the type was checked above.
+            Repr::Inline(buf, _) => Ok(unsafe { ptr::read(buf.get().cast::<T>()) }),
+            Repr::Boxed(boxed) => Ok(*boxed
+                .downcast()
+                .expect("downcast must succeed: the type was checked above")),
+            // SAFETY: the type was checked above. The memory is left for the
+            // arena to reclaim.
+            Repr::Arena(ptr, _) => Ok(unsafe { ptr::read(ptr.cast::<T>().as_ptr()) }),
+        }
+    }
+}
+
+impl Drop for Erased {
+    fn drop(&mut self) {
+        match &mut self.0 {
+            // SAFETY: the buffer holds a live value of the type described
+            // by `meta`, and it is never touched again.
+            Repr::Inline(buf, meta) => unsafe {
+                (meta.drop)(buf.get_mut().as_mut_ptr().cast::<u8>())
+            },
+            // SAFETY: as above, and the arena outlives `self`.
+            Repr::Arena(ptr, meta) => unsafe { (meta.drop)(ptr.as_ptr()) },
+            Repr::Boxed(_) => {}
+        }
+    }
+}
+
+impl Debug for Erased {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Any").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use super::*;
+
+    struct DropCounter(Rc<Cell<usize>>);
+
+    impl Drop for DropCounter {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    #[test]
+    fn small_values_are_stored_inline() {
+        assert!(Erased::new(1u8).is_inline());
+        assert!(Erased::new(1usize).is_inline());
+        assert!(Erased::new(String::new()).is_inline());
+        assert!(Erased::new(()).is_inline());
+        assert!(!Erased::new([0usize; INLINE_WORDS + 1]).is_inline());
+    }
+
+    #[test]
+    fn erased_values_can_be_downcast() {
+        let mut inline = Erased::new(7u32);
+        assert_eq!(inline.downcast_ref::<u32>(), Some(&7));
+        assert_eq!(inline.downcast_ref::<i32>(), None);
+        *inline.downcast_mut::<u32>().unwrap() += 1;
+        assert_eq!(inline.downcast::<u32>().ok(), Some(8));
+
+        let boxed = Erased::new([1u64; 8]);
+        assert_eq!(boxed.downcast_ref::<[u64; 8]>(), Some(&[1u64; 8]));
+        let boxed = boxed.downcast::<u8>().unwrap_err();
+        assert_eq!(boxed.downcast::<[u64; 8]>().ok(), Some([1u64; 8]));
+    }
+
+    #[test]
+    fn inline_values_allow_interior_mutability() {
+        let erased = Erased::new(Cell::new(1u32));
+        assert!(erased.is_inline());
+
+        let cell = erased.downcast_ref::<Cell<u32>>().unwrap();
+        cell.set(cell.get() + 1);
+        erased.downcast_ref::<Cell<u32>>().unwrap().set(3);
+        assert_eq!(cell.get(), 3);
+        assert_eq!(erased.downcast::<Cell<u32>>().unwrap().into_inner(), 3);
+    }
+
+    #[test]
+    fn erased_values_are_dropped_exactly_once() {
+        let drops = Rc::new(Cell::new(0));
+
+        drop(Erased::new(DropCounter(drops.clone())));
+        assert_eq!(drops.get(), 1);
+
+        let value = Erased::new(DropCounter(drops.clone()))
+            .downcast::<DropCounter>()
+            .ok();
+        assert_eq!(drops.get(), 1);
+        drop(value);
+        assert_eq!(drops.get(), 2);
+
+        drop(Erased::new((DropCounter(drops.clone()), [0usize; 4])));
+        assert_eq!(drops.get(), 3);
+    }
+
+    #[test]
+    fn arena_values_are_dropped_but_not_freed() {
+        let drops = Rc::new(Cell::new(0));
+        let mut arena = Arena::with_capacity(0);
+
+        let erased = Erased::new_in((DropCounter(drops.clone()), [0usize; 4]), &mut arena);
+        assert!(erased.downcast_ref::<(DropCounter, [usize; 4])>().is_some());
+        drop(erased);
+        assert_eq!(drops.get(), 1);
+
+        let erased = Erased::new_in([5u64; 8], &mut arena);
+        assert_eq!(erased.downcast::<[u64; 8]>().ok(), Some([5u64; 8]));
+        assert!(arena.capacity() > 0);
+    }
+}
@@ -0,0 +1,360 @@
+use std::{
+    any::Any,
+    fmt::{Debug, Formatter},
+    marker::PhantomData,
+};
+
+use indexmap::IndexMap;
+
+use crate::Type;
+
+/// Event queues keyed by the event type, with double buffering.
+///
+/// [`.send()`] appends an event to the queue of its type, and an
+/// [`EventReader`] from [`.reader()`] reads each event once. Every call to
+/// [`.update()`] retires the events sent before the previous update, so an
+/// event stays readable for exactly two update cycles: the one it was sent
+/// in and the next. Readers that check at least once per cycle see every
+/// event.
+///
+/// # Example
+///
+/// ```
+/// use singletons::Events;
+///
+/// struct PlayerJoined(&'static str);
+///
+/// let mut events = Events::new();
+/// let mut reader = events.reader::<PlayerJoined>();
+///
+/// events.send(PlayerJoined("ferris"));
+/// let names: Vec<_> = reader.read(&events).map(|event| event.0).collect();
+/// assert_eq!(names, ["ferris"]);
+///
+/// // Each event is only read once.
+/// assert_eq!(reader.read(&events).count(), 0);
+///
+/// events.send(PlayerJoined("corro"));
+/// events.update();
+/// assert_eq!(reader.read(&events).count(), 1);
+///
+/// events.update();
+/// assert_eq!(events.len::<PlayerJoined>(), 0);
+/// ```
+///
+/// [`.send()`]: Self::send()
+/// [`.reader()`]: Self::reader()
+/// [`.update()`]: Self::update()
+#[derive(Debug, Default)]
+pub struct Events(IndexMap<Type, Box<dyn Queue>>);
+
+/// The type-erased operations on an [`EventQueue`].
+trait Queue {
+    fn update(&mut self);
+    fn clear(&mut self);
+    fn len(&self) -> usize;
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl Debug for dyn Queue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventQueue")
+            .field("len", &self.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// The two buffers of events of a single type.
+struct EventQueue<E> {
+    /// Events sent before the latest update.
+    previous: Vec<E>,
+    /// Events sent since the latest update.
+    current: Vec<E>,
+    /// The sequence number of the first event in `previous`.
+    start: usize,
+}
+
+impl<E> EventQueue<E> {
+    /// The sequence number the next event will have.
+    fn end(&self) -> usize {
+        self.start + self.previous.len() + self.current.len()
+    }
+}
+
+impl<E: 'static> Queue for EventQueue<E> {
+    fn update(&mut self) {
+        self.start += self.previous.len();
+        std::mem::swap(&mut self.previous, &mut self.current);
+        self.current.clear();
+    }
+
+    fn clear(&mut self) {
+        self.start = self.end();
+        self.previous.clear();
+        self.current.clear();
+    }
+
+    fn len(&self) -> usize {
+        self.previous.len() + self.current.len()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+impl Events {
+    /// Creates an empty set of event queues.
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        Events(IndexMap::new())
+    }
+
+    /// Returns an iterator that visits the type of each queue, in the order
+    /// the first event of each type was sent.
+    pub fn types(&self) -> impl ExactSizeIterator<Item = &Type> + DoubleEndedIterator + '_ {
+        self.0.keys()
+    }
+
+    /// Returns the number of readable events of the specified type.
+    #[must_use]
+    pub fn len<E>(&self) -> usize
+    where
+        E: 'static,
+    {
+        self.queue::<E>().map_or(0, Queue::len)
+    }
+
+    /// Returns true if there are no readable events of the specified type.
+    #[must_use]
+    pub fn is_empty<E>(&self) -> bool
+    where
+        E: 'static,
+    {
+        self.len::<E>() == 0
+    }
+
+    /// Appends an event to the queue of its type.
+    pub fn send<E>(&mut self, event: E)
+    where
+        E: 'static,
+    {
+        self.0
+            .entry(Type::of::<E>())
+            .or_insert_with(|| {
+                Box::new(EventQueue::<E> {
+                    previous: Vec::new(),
+                    current: Vec::new(),
+                    start: 0,
+                })
+            })
+            .as_any_mut()
+            .downcast_mut::<EventQueue<E>>()
+            .expect("queues are stored under their event type")
+            .current
+            .push(event);
+    }
+
+    /// Returns a reader for events of the specified type, starting with the
+    /// oldest event that is still readable.
+    #[must_use]
+    pub fn reader<E>(&self) -> EventReader<E>
+    where
+        E: 'static,
+    {
+        EventReader {
+            next: self.queue::<E>().map_or(0, |queue| queue.start),
+            marker: PhantomData,
+        }
+    }
+
+    /// Swaps the buffers of every queue, dropping the events that were sent
+    /// before the previous update.
+    ///
+    /// This is usually called once per frame.
+    pub fn update(&mut self) {
+        for queue in self.0.values_mut() {
+            queue.update();
+        }
+    }
+
+    /// Drops every event without reading it.
+    ///
+    /// Readers skip the dropped events, as if they had been read.
+    pub fn clear(&mut self) {
+        for queue in self.0.values_mut() {
+            queue.clear();
+        }
+    }
+
+    fn queue<E: 'static>(&self) -> Option<&EventQueue<E>> {
+        self.0.get(&Type::of::<E>()).map(|queue| {
+            queue
+                .as_any()
+                .downcast_ref()
+                .expect("queues are stored under their event type")
+        })
+    }
+}
+
+/// A cursor that reads each event of type `E` in [`Events`] once.
+///
+/// Created by [`Events::reader()`]. The reader only stores its position, so
+/// it can be kept across frames without borrowing the [`Events`].
+pub struct EventReader<E> {
+    /// The sequence number of the next event to read.
+    next: usize,
+    marker: PhantomData<fn() -> E>,
+}
+
+impl<E> Clone for EventReader<E> {
+    fn clone(&self) -> Self {
+        EventReader {
+            next: self.next,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<E> Debug for EventReader<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventReader")
+            .field("next", &self.next)
+            .finish()
+    }
+}
+
+impl<E> EventReader<E>
+where
+    E: 'static,
+{
+    /// Returns an iterator over the events that this reader has not read
+    /// yet, oldest first, and marks them as read.
+    ///
+    /// Events that were dropped by [`Events::update()`] before this reader
+    /// got to them are skipped.
+    pub fn read<'a>(&mut self, events: &'a Events) -> EventIter<'a, E> {
+        let queue = match events.queue::<E>() {
+            Some(queue) => queue,
+            None => return EventIter([].iter().chain([].iter())),
+        };
+        let skip = self.next.saturating_sub(queue.start);
+        self.next = queue.end();
+
+        let previous = queue.previous.get(skip..).unwrap_or_default();
+        let skip = skip.saturating_sub(queue.previous.len());
+        let current = queue.current.get(skip..).unwrap_or_default();
+        EventIter(previous.iter().chain(current.iter()))
+    }
+
+    /// Returns the number of events that this reader has not read yet.
+    #[must_use]
+    pub fn len(&self, events: &Events) -> usize {
+        events.queue::<E>().map_or(0, |queue| {
+            queue.end() - self.next.clamp(queue.start, queue.end())
+        })
+    }
+
+    /// Returns true if this reader has read every event.
+    #[must_use]
+    pub fn is_empty(&self, events: &Events) -> bool {
+        self.len(events) == 0
+    }
+}
+
+/// An iterator over the unread events of a type.
+///
+/// Created by [`EventReader::read()`].
+#[derive(Debug)]
+pub struct EventIter<'a, E>(std::iter::Chain<std::slice::Iter<'a, E>, std::slice::Iter<'a, E>>);
+
+impl<'a, E> Clone for EventIter<'a, E> {
+    fn clone(&self) -> Self {
+        EventIter(self.0.clone())
+    }
+}
+
+impl<'a, E> Iterator for EventIter<'a, E> {
+    type Item = &'a E;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<'a, E> ExactSizeIterator for EventIter<'a, E> {}
+
+impl<'a, E> DoubleEndedIterator for EventIter<'a, E> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_live_for_two_updates() {
+        let mut events = Events::new();
+        events.send(1u8);
+        events.update();
+        events.send(2u8);
+
+        let mut late = events.reader::<u8>();
+        assert_eq!(late.len(&events), 2);
+        assert_eq!(late.read(&events).copied().collect::<Vec<_>>(), [1, 2]);
+
+        events.update();
+        events.send(3u8);
+        assert_eq!(events.len::<u8>(), 2);
+        assert_eq!(late.read(&events).copied().collect::<Vec<_>>(), [3]);
+        assert!(late.is_empty(&events));
+    }
+
+    #[test]
+    fn slow_readers_skip_dropped_events() {
+        let mut events = Events::new();
+        let mut reader = events.reader::<&str>();
+        events.send("dropped");
+        events.update();
+        events.send("kept");
+        events.update();
+        events.update();
+        events.send("new");
+
+        assert_eq!(reader.read(&events).collect::<Vec<_>>(), [&"new"]);
+    }
+
+    #[test]
+    fn queues_are_independent_by_type() {
+        let mut events = Events::new();
+        let mut bytes = events.reader::<u8>();
+        let mut words = events.reader::<u16>();
+        events.send(1u8);
+        events.send(2u16);
+        events.send(3u16);
+
+        assert_eq!(bytes.read(&events).len(), 1);
+        assert_eq!(words.read(&events).next_back(), Some(&3));
+        assert_eq!(events.types().len(), 2);
+
+        events.send(4u8);
+        events.clear();
+        assert!(events.is_empty::<u8>());
+        assert!(bytes.is_empty(&events));
+    }
+}
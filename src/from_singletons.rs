@@ -0,0 +1,87 @@
+use std::fmt::{Display, Formatter};
+
+use crate::{SingletonSet, Type};
+
+/// A type that can be assembled from the values in a [`SingletonSet`].
+///
+/// This is usually implemented with `#[derive(FromSingletons)]`, which is
+/// available with the `derive` feature. The derive macro supports structs
+/// whose fields are references to values in the set (`&'a T`), clones of
+/// them (`T`), or optional versions of either (`Option<&'a T>` or
+/// `Option<T>`), and also generates an inherent `from_set()` method so the
+/// trait does not need to be in scope.
+///
+/// # Example
+///
+/// ```
+/// # #[cfg(feature = "derive")]
+/// # {
+/// use singletons::{FromSingletons, SingletonSet};
+///
+/// struct Database(&'static str);
+/// #[derive(Clone)]
+/// struct Config(u16);
+/// struct Metrics;
+///
+/// #[derive(FromSingletons)]
+/// struct Deps<'a> {
+///     db: &'a Database,
+///     config: Config,
+///     metrics: Option<&'a Metrics>,
+/// }
+///
+/// let mut set = SingletonSet::new();
+/// set.insert(Database("postgres://"));
+/// set.insert(Config(8080));
+///
+/// let deps = Deps::from_set(&set).unwrap();
+/// assert_eq!(deps.db.0, "postgres://");
+/// assert_eq!(deps.config.0, 8080);
+/// assert!(deps.metrics.is_none());
+/// # }
+/// ```
+pub trait FromSingletons<'a>: Sized {
+    /// Assembles `Self` from the values in `set`.
+    ///
+    /// If any required values are absent, the error lists every missing
+    /// type, not just the first.
+    fn from_set(set: &'a SingletonSet) -> Result<Self, MissingTypes>;
+}
+
+/// The error returned when values required from a [`SingletonSet`] are
+/// absent.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MissingTypes(Vec<Type>);
+
+impl MissingTypes {
+    /// Creates an error for the given missing types.
+    ///
+    /// This is used by `#[derive(FromSingletons)]` and is not part of the
+    /// public API.
+    #[doc(hidden)]
+    #[must_use]
+    pub fn new(types: Vec<Type>) -> Self {
+        MissingTypes(types)
+    }
+
+    /// Returns the missing types, in the order they were requested.
+    #[must_use]
+    pub fn types(&self) -> &[Type] {
+        &self.0
+    }
+}
+
+impl Display for MissingTypes {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("missing types in SingletonSet: ")?;
+        for (i, ty) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            f.write_str(ty.as_name())?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for MissingTypes {}
@@ -0,0 +1,541 @@
+use std::{
+    any::TypeId,
+    collections::HashMap,
+    fmt::{self, Debug, Formatter},
+    hash::{Hash, Hasher},
+    ops::Range,
+};
+
+use crate::{SharedSingletonSet, SingletonSet, Storage, Type};
+
+/// The average number of keys per bucket while building the lookup table.
+const KEYS_PER_BUCKET: usize = 4;
+
+/// The number of seeds to try before falling back to a plain map.
+const MAX_SEEDS: u64 = 16;
+
+/// The number of displacements to try for each bucket with a single seed.
+const MAX_DISPLACEMENTS: usize = 1024;
+
+/// A read-only set that stores one value per type, with a collision-free
+/// lookup table over the contained types.
+///
+/// A frozen set is created from a [`SingletonSet`] or a
+/// [`SharedSingletonSet`] once it no longer needs to change, and can be
+/// turned back into one with [`.thaw()`]. Looking up a type hashes its
+/// [`TypeId`] once and then reads exactly one table entry, without probing.
+///
+/// The type parameter is the set it was frozen from, which keeps its values
+/// where they are. A set frozen from a [`SingletonSet`] is `Send + Sync` if
+/// the source has [`ThreadSafe`] storage, and a set frozen from a
+/// [`SharedSingletonSet`] always is.
+///
+/// # Example
+///
+/// ```
+/// use singletons::SingletonSet;
+///
+/// let mut set = SingletonSet::new();
+/// set.insert(42u32);
+/// set.insert("hello");
+///
+/// let frozen = set.freeze();
+/// assert_eq!(frozen.get::<u32>(), &42);
+/// assert_eq!(frozen.try_get::<i32>(), None);
+///
+/// let mut set = frozen.thaw();
+/// set.insert(7i32);
+/// ```
+///
+/// [`ThreadSafe`]: crate::ThreadSafe
+/// [`.thaw()`]: Self::thaw()
+pub struct FrozenSingletonSet<S: Freezable = SingletonSet> {
+    values: S::Values,
+    lookup: Lookup,
+}
+
+/// A set that can be frozen into a [`FrozenSingletonSet`].
+///
+/// This trait is sealed and implemented for [`SingletonSet`] and
+/// [`SharedSingletonSet`].
+pub trait Freezable: sealed::Sealed {}
+
+impl<S: Storage> Freezable for SingletonSet<S> {}
+
+impl Freezable for SharedSingletonSet {}
+
+mod sealed {
+    use std::{any::Any, sync::Arc};
+
+    use indexmap::IndexMap;
+
+    use crate::{SharedSingletonSet, SingletonSet, Storage, Type};
+
+    pub trait Sealed: Sized {
+        /// The values of a frozen set, in insertion order.
+        type Values;
+
+        fn into_values(self) -> Self::Values;
+
+        fn from_values(values: Self::Values) -> Self;
+
+        fn len(values: &Self::Values) -> usize;
+
+        fn type_at(values: &Self::Values, index: usize) -> Option<&Type>;
+
+        fn value_at<T: 'static>(values: &Self::Values, index: usize) -> Option<&T>;
+    }
+
+    impl<S: Storage> Sealed for SingletonSet<S> {
+        type Values = Self;
+
+        fn into_values(self) -> Self {
+            self
+        }
+
+        fn from_values(values: Self) -> Self {
+            values
+        }
+
+        fn len(values: &Self) -> usize {
+            values.len()
+        }
+
+        fn type_at(values: &Self, index: usize) -> Option<&Type> {
+            values.type_at(index)
+        }
+
+        fn value_at<T: 'static>(values: &Self, index: usize) -> Option<&T> {
+            values.get_index(index)
+        }
+    }
+
+    impl Sealed for SharedSingletonSet {
+        type Values = IndexMap<Type, Arc<dyn Any + Send + Sync>>;
+
+        fn into_values(self) -> Self::Values {
+            self.into_values()
+        }
+
+        fn from_values(values: Self::Values) -> Self {
+            SharedSingletonSet::from_values(values)
+        }
+
+        fn len(values: &Self::Values) -> usize {
+            values.len()
+        }
+
+        fn type_at(values: &Self::Values, index: usize) -> Option<&Type> {
+            values.get_index(index).map(|(ty, _)| ty)
+        }
+
+        fn value_at<T: 'static>(values: &Self::Values, index: usize) -> Option<&T> {
+            values
+                .get_index(index)
+                .and_then(|(_, value)| value.downcast_ref())
+        }
+    }
+}
+
+impl<S: Freezable> FrozenSingletonSet<S> {
+    pub(crate) fn new(set: S) -> Self {
+        let values = set.into_values();
+        let ids: Vec<TypeId> = (0..S::len(&values))
+            .filter_map(|index| S::type_at(&values, index))
+            .map(Type::to_id)
+            .collect();
+        FrozenSingletonSet {
+            values,
+            lookup: Lookup::new(&ids),
+        }
+    }
+
+    /// Returns the number of values in the set.
+    #[inline]
+    #[must_use]
+    pub fn len(&self) -> usize {
+        S::len(&self.values)
+    }
+
+    /// Returns true if the set contains no values.
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns true if the type is represented in the set.
+    #[must_use]
+    pub fn contains<T>(&self) -> bool
+    where
+        T: 'static,
+    {
+        self.find(&TypeId::of::<T>()).is_some()
+    }
+
+    /// Returns true if the supplied [`Type`] is represented in the set.
+    #[must_use]
+    pub fn contains_type(&self, t: &Type) -> bool {
+        self.find(t.as_id()).is_some()
+    }
+
+    /// Returns an immutable reference to the value of the specified type.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if there is no value for the given type. If this
+    /// is not acceptable, use [`.try_get()`].
+    ///
+    /// [`.try_get()`]: Self::try_get()
+    #[track_caller]
+    #[must_use]
+    pub fn get<T>(&self) -> &T
+    where
+        T: 'static,
+    {
+        self.try_get()
+            .expect(".try_get() should be used if the slot might be empty")
+    }
+
+    /// Returns an immutable reference to the value of the specified type,
+    /// if it exists.
+    #[must_use]
+    pub fn try_get<T>(&self) -> Option<&T>
+    where
+        T: 'static,
+    {
+        self.find(&TypeId::of::<T>())
+            .and_then(|index| S::value_at(&self.values, index))
+    }
+
+    /// Returns an iterator that visits each [`Type`] in the set in the
+    /// insertion order of the set it was frozen from.
+    #[must_use]
+    pub fn types(&self) -> FrozenTypes<'_, S> {
+        FrozenTypes {
+            values: &self.values,
+            range: 0..self.len(),
+        }
+    }
+
+    /// Converts the frozen set back into the set it was frozen from,
+    /// preserving the insertion order.
+    ///
+    /// A [`SingletonSet`] gets back its arena and deep-size registrations
+    /// along with its values.
+    #[must_use]
+    pub fn thaw(self) -> S {
+        S::from_values(self.values)
+    }
+
+    fn find(&self, id: &TypeId) -> Option<usize> {
+        let index = self.lookup.index_of(id)?;
+        match S::type_at(&self.values, index) {
+            Some(ty) if ty.as_id() == id => Some(index),
+            _ => None,
+        }
+    }
+}
+
+impl<S: Freezable> Debug for FrozenSingletonSet<S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.types()).finish()
+    }
+}
+
+/// An iterator of the [`Type`]s in a [`FrozenSingletonSet`].
+pub struct FrozenTypes<'a, S: Freezable> {
+    values: &'a S::Values,
+    range: Range<usize>,
+}
+
+impl<'a, S: Freezable> Clone for FrozenTypes<'a, S> {
+    fn clone(&self) -> Self {
+        FrozenTypes {
+            values: self.values,
+            range: self.range.clone(),
+        }
+    }
+}
+
+impl<'a, S: Freezable> Iterator for FrozenTypes<'a, S> {
+    type Item = &'a Type;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.range
+            .next()
+            .and_then(|index| S::type_at(self.values, index))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.range.size_hint()
+    }
+}
+
+impl<'a, S: Freezable> ExactSizeIterator for FrozenTypes<'a, S> {
+    fn len(&self) -> usize {
+        self.range.len()
+    }
+}
+
+impl<'a, S: Freezable> DoubleEndedIterator for FrozenTypes<'a, S> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.range
+            .next_back()
+            .and_then(|index| S::type_at(self.values, index))
+    }
+}
+
+/// Maps each [`TypeId`] in a frozen set to the only index that can hold it.
+enum Lookup {
+    /// A minimal perfect hash table.
+    Perfect {
+        /// The index of the value for each position of the table.
+        table: Box<[u32]>,
+        /// The displacements for each bucket of the table.
+        displacements: Displacements,
+        seed: u64,
+    },
+    /// A plain hash map, used when no perfect hash table was found within
+    /// the attempt limits.
+    Map(HashMap<TypeId, usize>),
+}
+
+type Displacements = Box<[(u32, u32)]>;
+
+impl Lookup {
+    fn new(ids: &[TypeId]) -> Self {
+        (0..MAX_SEEDS)
+            .find_map(|seed| {
+                build(ids, seed).map(|(table, displacements)| Lookup::Perfect {
+                    table,
+                    displacements,
+                    seed,
+                })
+            })
+            .unwrap_or_else(|| {
+                Lookup::Map(ids.iter().enumerate().map(|(i, id)| (*id, i)).collect())
+            })
+    }
+
+    fn index_of(&self, id: &TypeId) -> Option<usize> {
+        match self {
+            Lookup::Perfect { table, .. } if table.is_empty() => None,
+            Lookup::Perfect {
+                table,
+                displacements,
+                seed,
+            } => {
+                let hashes = Hashes::new(id, *seed);
+                let (d1, d2) = displacements[hashes.bucket(displacements.len())];
+                Some(table[hashes.index(d1, d2, table.len())] as usize)
+            }
+            Lookup::Map(map) => map.get(id).copied(),
+        }
+    }
+}
+
+/// The hashes of a single key, derived from one pass over its [`TypeId`].
+struct Hashes {
+    g: u32,
+    f1: u32,
+    f2: u32,
+}
+
+impl Hashes {
+    fn new(id: &TypeId, seed: u64) -> Self {
+        let mut hasher = SeededHasher(seed);
+        id.hash(&mut hasher);
+        let hash = hasher.finish();
+        let extra = mix(hash ^ 0x9e37_79b9_7f4a_7c15);
+        Hashes {
+            g: (hash >> 32) as u32,
+            f1: hash as u32,
+            f2: extra as u32,
+        }
+    }
+
+    fn bucket(&self, buckets: usize) -> usize {
+        self.g as usize % buckets
+    }
+
+    fn index(&self, d1: u32, d2: u32, len: usize) -> usize {
+        (d2.wrapping_add(self.f1.wrapping_mul(d1))
+            .wrapping_add(self.f2)) as usize
+            % len
+    }
+}
+
+/// Builds a minimal perfect hash table for `ids` with the
+/// hash-and-displace algorithm, returning [`None`] if `seed` does not work
+/// within [`MAX_DISPLACEMENTS`] attempts for some bucket.
+fn build(ids: &[TypeId], seed: u64) -> Option<(Box<[u32]>, Displacements)> {
+    let len = ids.len();
+    if len == 0 {
+        return Some((Box::new([]), Box::new([])));
+    }
+
+    let hashes: Vec<Hashes> = ids.iter().map(|id| Hashes::new(id, seed)).collect();
+    let bucket_count = (len + KEYS_PER_BUCKET - 1) / KEYS_PER_BUCKET;
+    let mut buckets: Vec<(usize, Vec<usize>)> =
+        (0..bucket_count).map(|i| (i, Vec::new())).collect();
+    for (key, hash) in hashes.iter().enumerate() {
+        buckets[hash.bucket(bucket_count)].1.push(key);
+    }
+    // Place the largest buckets first, while the table is still empty.
+    buckets.sort_by_key(|(_, keys)| std::cmp::Reverse(keys.len()));
+
+    let mut table: Vec<Option<u32>> = vec![None; len];
+    let mut displacements = vec![(0u32, 0u32); bucket_count];
+    let mut claimed = Vec::with_capacity(KEYS_PER_BUCKET);
+
+    let attempts = len.saturating_mul(len).min(MAX_DISPLACEMENTS);
+    'buckets: for (bucket, keys) in &buckets {
+        'displacements: for attempt in 0..attempts {
+            let (d1, d2) = ((attempt / len) as u32, (attempt % len) as u32);
+            claimed.clear();
+            for &key in keys {
+                let index = hashes[key].index(d1, d2, len);
+                if table[index].is_some() || claimed.contains(&index) {
+                    continue 'displacements;
+                }
+                claimed.push(index);
+            }
+            for (&key, &index) in keys.iter().zip(&claimed) {
+                table[index] = Some(key as u32);
+            }
+            displacements[*bucket] = (d1, d2);
+            continue 'buckets;
+        }
+        return None;
+    }
+
+    let table = table
+        .into_iter()
+        .map(|key| key.expect("every position is filled when there are as many keys"))
+        .collect();
+    Some((table, displacements.into_boxed_slice()))
+}
+
+/// A small, fast hasher for the handful of words that make up a [`TypeId`].
+struct SeededHasher(u64);
+
+impl Hasher for SeededHasher {
+    fn finish(&self) -> u64 {
+        mix(self.0)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for chunk in bytes.chunks(8) {
+            let mut word = [0; 8];
+            word[..chunk.len()].copy_from_slice(chunk);
+            self.write_u64(u64::from_le_bytes(word));
+        }
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.0 = (self.0 ^ i)
+            .wrapping_mul(0x9e37_79b9_7f4a_7c15)
+            .rotate_left(29);
+    }
+}
+
+/// The `splitmix64` finalizer.
+fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Marker<const N: usize>;
+
+    macro_rules! insert_markers {
+        ($set:expr, $($n:literal)*) => {
+            $( $set.insert(Marker::<$n>); )*
+        };
+    }
+
+    #[test]
+    fn frozen_singletonset_finds_every_type() {
+        let mut set = SingletonSet::new();
+        insert_markers!(set, 0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24);
+        set.insert(7u8);
+        set.insert("str");
+
+        let frozen = set.freeze();
+        assert_eq!(frozen.len(), 27);
+        assert!(frozen.contains::<Marker<0>>());
+        assert!(frozen.contains::<Marker<24>>());
+        assert!(!frozen.contains::<Marker<25>>());
+        assert_eq!(frozen.get::<u8>(), &7);
+        assert_eq!(frozen.get::<&str>(), &"str");
+        assert_eq!(frozen.try_get::<u16>(), None);
+        assert_eq!(frozen.types().next(), Some(&Type::of::<Marker<0>>()));
+
+        let set = frozen.thaw();
+        assert_eq!(set.len(), 27);
+        assert_eq!(set.get::<u8>(), &7);
+        assert_eq!(set.types().next_back(), Some(&Type::of::<&str>()));
+    }
+
+    #[test]
+    fn frozen_thread_safe_singletonset_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+        let mut set = SingletonSet::thread_safe();
+        set.insert(1u32);
+        set.insert(String::from("local"));
+
+        let frozen = set.freeze();
+        assert_send_sync(&frozen);
+        std::thread::scope(|scope| {
+            scope.spawn(|| assert_eq!(frozen.get::<u32>(), &1));
+            scope.spawn(|| assert_eq!(frozen.get::<String>(), "local"));
+        });
+
+        let mut set = frozen.thaw();
+        set.insert(2u64);
+        assert_eq!(set.len(), 3);
+    }
+
+    #[test]
+    fn lookup_falls_back_to_a_map() {
+        let ids = [TypeId::of::<u8>(), TypeId::of::<u16>(), TypeId::of::<u32>()];
+        assert!(matches!(Lookup::new(&ids), Lookup::Perfect { .. }));
+
+        // Duplicate keys can never be placed in a perfect hash table.
+        let lookup = Lookup::new(&[ids[0], ids[1], ids[0]]);
+        assert!(matches!(lookup, Lookup::Map(_)));
+        assert_eq!(lookup.index_of(&ids[1]), Some(1));
+        assert_eq!(lookup.index_of(&ids[2]), None);
+    }
+
+    #[test]
+    fn frozen_singletonset_can_be_empty() {
+        let frozen = SingletonSet::new().freeze();
+        assert!(frozen.is_empty());
+        assert_eq!(frozen.try_get::<u8>(), None);
+    }
+
+    #[test]
+    fn frozen_shared_singletonset_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+        let set = SharedSingletonSet::new();
+        set.insert(1u32);
+        set.insert(String::from("shared"));
+
+        let frozen = set.freeze();
+        assert_send_sync(&frozen);
+        std::thread::scope(|scope| {
+            scope.spawn(|| assert_eq!(frozen.get::<u32>(), &1));
+            scope.spawn(|| assert_eq!(frozen.get::<String>(), "shared"));
+        });
+
+        assert_eq!(frozen.thaw().get::<u32>().as_deref(), Some(&1));
+    }
+}
@@ -1,5 +1,6 @@
 use std::{
     any::TypeId,
+    cmp::Ordering,
     fmt::{Display, Formatter},
     hash::Hasher,
 };
@@ -19,10 +20,24 @@ use std::{
 /// the first open angle bracket (`<`) and the nearest colon (`:`) to the
 /// left of it.
 ///
+/// The size and alignment of the type are also captured, and are available
+/// from [`.size_of()`] and [`.align_of()`].
+///
+/// Types are ordered by their full name, falling back to the [`TypeId`] for
+/// distinct types that share a name, so sorted collections of types iterate
+/// in the same order from one run to the next.
+///
 /// [`.as_str()`]: Self::as_str()
 /// [`.as_name()`]: Self::as_name()
+/// [`.size_of()`]: Self::size_of()
+/// [`.align_of()`]: Self::align_of()
 #[derive(Clone, Copy, Debug, Eq)]
-pub struct Type(TypeId, &'static str);
+pub struct Type {
+    id: TypeId,
+    name: &'static str,
+    size: usize,
+    align: usize,
+}
 
 impl Type {
     /// Creates a new `Type`
@@ -30,19 +45,24 @@ impl Type {
     where
         T: 'static,
     {
-        Type(TypeId::of::<T>(), std::any::type_name::<T>())
+        Type {
+            id: TypeId::of::<T>(),
+            name: std::any::type_name::<T>(),
+            size: std::mem::size_of::<T>(),
+            align: std::mem::align_of::<T>(),
+        }
     }
 
     /// Returns a [`TypeId`] representing the type uniquely among all other
     /// types available to the compiler.
     pub fn as_id(&self) -> &TypeId {
-        &self.0
+        &self.id
     }
 
     /// Returns a [`TypeId`] representing the type uniquely among all other
     /// types available to the compiler.
     pub fn to_id(&self) -> TypeId {
-        self.0
+        self.id
     }
 
     /// Returns a name of the type as a string, as reported by the compiler.
@@ -50,7 +70,15 @@ impl Type {
     /// Type names are not unique, and there may be multiple type names that
     /// all refer to the same type.
     pub fn as_str(&self) -> &str {
-        self.1
+        self.name
+    }
+
+    /// Returns the same name as [`.as_str()`], with its `'static` lifetime.
+    ///
+    /// [`.as_str()`]: Self::as_str()
+    #[cfg(feature = "map")]
+    pub(crate) fn static_str(&self) -> &'static str {
+        self.name
     }
 
     // NOTE: `to_str` is not implemented as a convenience method because the
@@ -63,11 +91,11 @@ impl Type {
     /// The short type name is not guaranteed to be consistent across
     /// multiple builds, or unique among available types.
     pub fn as_name(&self) -> &str {
-        let to_index = self.1.find('<').unwrap_or(self.1.len());
+        let to_index = self.name.find('<').unwrap_or(self.name.len());
 
-        let from_index = self.1[..to_index].rfind(':').map_or(0, |i| i + 1);
+        let from_index = self.name[..to_index].rfind(':').map_or(0, |i| i + 1);
 
-        &self.1[from_index..to_index]
+        &self.name[from_index..to_index]
     }
 
     /// Returns a short name of the type as a string.
@@ -77,6 +105,22 @@ impl Type {
     pub fn to_name(&self) -> String {
         self.as_name().to_string()
     }
+
+    /// Returns the size of the type in bytes, as reported by
+    /// [`std::mem::size_of()`].
+    ///
+    /// This is the shallow size of a value of the type, which does not
+    /// include any memory the value owns indirectly, such as heap
+    /// allocations.
+    pub fn size_of(&self) -> usize {
+        self.size
+    }
+
+    /// Returns the minimum alignment of the type in bytes, as reported by
+    /// [`std::mem::align_of()`].
+    pub fn align_of(&self) -> usize {
+        self.align
+    }
 }
 
 impl AsRef<str> for Type {
@@ -102,7 +146,7 @@ impl std::hash::Hash for Type {
         // The TypeId is guaranteed to be unique, so that's all that should
         // be hashed. The name has weaker guarantees and comes from the same
         // compiler at the same time.
-        self.0.hash(state)
+        self.id.hash(state)
     }
 }
 
@@ -111,6 +155,22 @@ impl PartialEq for Type {
         // The TypeId is guaranteed to be unique, so that's all that should
         // be hashed. The name has weaker guarantees and comes from the same
         // compiler at the same time.
-        self.0 == other.0
+        self.id == other.id
+    }
+}
+
+impl PartialOrd for Type {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Type {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Equal TypeIds always come with equal names, so this agrees with
+        // `PartialEq`.
+        self.name
+            .cmp(other.name)
+            .then_with(|| self.id.cmp(&other.id))
     }
 }
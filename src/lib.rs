@@ -4,12 +4,91 @@
 mod key;
 pub use key::Type;
 
+mod macros;
+
+#[cfg(any(feature = "set", feature = "map"))]
+mod memory;
+#[cfg(any(feature = "set", feature = "map"))]
+pub use memory::*;
+
+#[cfg(feature = "set")]
+mod arena;
+#[cfg(feature = "set")]
+mod builder;
+#[cfg(feature = "set")]
+pub use builder::*;
+#[cfg(feature = "set")]
+mod erased;
+#[cfg(feature = "set")]
+mod from_singletons;
+#[cfg(feature = "set")]
+pub use from_singletons::*;
+#[cfg(feature = "derive")]
+pub use singletons_derive::FromSingletons;
+#[cfg(feature = "set")]
+mod events;
+#[cfg(feature = "set")]
+pub use events::*;
+#[cfg(feature = "set")]
+mod frozen;
+#[cfg(feature = "set")]
+pub use frozen::*;
+#[cfg(feature = "set")]
+mod multiset;
+#[cfg(feature = "set")]
+pub use multiset::*;
+#[cfg(feature = "set")]
+mod parallel;
+#[cfg(feature = "set")]
+pub use parallel::*;
+#[cfg(feature = "set")]
+mod resources;
+#[cfg(feature = "set")]
+pub use resources::*;
 #[cfg(feature = "set")]
 mod set;
 #[cfg(feature = "set")]
 pub use set::*;
+#[cfg(feature = "set")]
+mod shared;
+#[cfg(feature = "set")]
+pub use shared::*;
+#[cfg(feature = "set")]
+mod system;
+#[cfg(feature = "set")]
+pub use system::*;
 
+#[cfg(feature = "map")]
+mod diff;
+#[cfg(feature = "map")]
+pub use diff::*;
+#[cfg(feature = "map")]
+mod dispatcher;
+#[cfg(feature = "map")]
+pub use dispatcher::*;
 #[cfg(feature = "map")]
 mod map;
 #[cfg(feature = "map")]
 pub use map::*;
+#[cfg(feature = "map")]
+mod pair;
+#[cfg(feature = "map")]
+pub use pair::*;
+#[cfg(feature = "map")]
+mod sorted;
+#[cfg(feature = "map")]
+pub use sorted::*;
+#[cfg(feature = "map")]
+mod typemap;
+#[cfg(feature = "map")]
+pub use typemap::*;
+
+#[cfg(feature = "rayon")]
+mod rayon;
+#[cfg(feature = "rayon")]
+pub use self::rayon::*;
+
+#[cfg(feature = "serde")]
+mod serde;
+#[cfg(feature = "serde")]
+pub use self::serde::*;
@@ -0,0 +1,80 @@
+/// Creates a [`SingletonSet`] containing the given values.
+///
+/// Each value is inserted in order with [`SingletonSet::insert()`], so a
+/// later value replaces an earlier value of the same type. To detect such
+/// duplicates instead, use [`SingletonSetBuilder`].
+///
+/// # Example
+///
+/// ```
+/// use singletons::singleton_set;
+///
+/// let set = singleton_set![42u32, "hello", 3.5f64];
+/// assert_eq!(set.len(), 3);
+/// assert_eq!(set.get::<u32>(), &42);
+/// ```
+///
+/// [`SingletonSet`]: crate::SingletonSet
+/// [`SingletonSet::insert()`]: crate::SingletonSet::insert()
+/// [`SingletonSetBuilder`]: crate::SingletonSetBuilder
+#[cfg(feature = "set")]
+#[macro_export]
+macro_rules! singleton_set {
+    (@count $($value:expr),+) => {
+        <[()]>::len(&[$($crate::singleton_set!(@unit $value)),+])
+    };
+    (@unit $value:expr) => {
+        ()
+    };
+    () => {
+        $crate::SingletonSet::new()
+    };
+    ($($value:expr),+ $(,)?) => {{
+        let mut set = $crate::SingletonSet::with_capacity($crate::singleton_set!(@count $($value),+));
+        $(
+            set.insert($value);
+        )+
+        set
+    }};
+}
+
+/// Creates a [`SingletonMap`] from a list of type keys and values.
+///
+/// Each entry is inserted in order with [`SingletonMap::insert()`], so a
+/// later entry replaces an earlier entry for the same type.
+///
+/// # Example
+///
+/// ```
+/// use singletons::singleton_map;
+///
+/// let map = singleton_map! {
+///     u8 => "byte",
+///     String => "text",
+/// };
+/// assert_eq!(map.get::<u8>(), Some(&"byte"));
+/// assert_eq!(map.get::<String>(), Some(&"text"));
+/// ```
+///
+/// [`SingletonMap`]: crate::SingletonMap
+/// [`SingletonMap::insert()`]: crate::SingletonMap::insert()
+#[cfg(feature = "map")]
+#[macro_export]
+macro_rules! singleton_map {
+    (@count $($key:ty),+) => {
+        <[()]>::len(&[$($crate::singleton_map!(@unit $key)),+])
+    };
+    (@unit $key:ty) => {
+        ()
+    };
+    () => {
+        $crate::SingletonMap::new()
+    };
+    ($($key:ty => $value:expr),+ $(,)?) => {{
+        let mut map = $crate::SingletonMap::with_capacity($crate::singleton_map!(@count $($key),+));
+        $(
+            map.insert::<$key>($value);
+        )+
+        map
+    }};
+}
@@ -1,7 +1,14 @@
+use std::{
+    cmp::Ordering,
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    ops::{Index, IndexMut, RangeBounds},
+};
+
 use indexmap::IndexMap;
 pub use indexmap::TryReserveError;
 
-use crate::Type;
+use crate::{DeepSize, MemoryReport, MemoryUsage, Type};
 
 /// A map that uses types as keys and stores values of a single type `V`.
 ///
@@ -22,7 +29,7 @@ use crate::Type;
 /// assert_eq!(map.get::<i8>(), Some(&"A signed 8-bit integer".to_string()));
 /// ```
 #[derive(Debug)]
-pub struct SingletonMap<V>(IndexMap<Type, V>);
+pub struct SingletonMap<V>(pub(crate) IndexMap<Type, V>);
 
 impl<V> SingletonMap<V> {
     /// Creates an empty `SingletonMap`.
@@ -182,6 +189,130 @@ impl<V> SingletonMap<V> {
         self.0.shift_remove(&Type::of::<K>())
     }
 
+    /// Removes a key-value pair from the map by swapping it with the last
+    /// pair, returning the value if the type key `K` was present.
+    ///
+    /// Unlike [`.remove()`], this takes constant time, but it changes the
+    /// position of the last pair.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use singletons::SingletonMap;
+    ///
+    /// let mut map = SingletonMap::new();
+    /// map.insert::<u8>("eight");
+    /// map.insert::<u16>("sixteen");
+    /// map.insert::<u32>("thirty-two");
+    ///
+    /// assert_eq!(map.swap_remove::<u8>(), Some("eight"));
+    /// assert_eq!(map.values().copied().collect::<Vec<_>>(), ["thirty-two", "sixteen"]);
+    /// ```
+    ///
+    /// [`.remove()`]: Self::remove()
+    pub fn swap_remove<K: 'static>(&mut self) -> Option<V> {
+        self.0.swap_remove(&Type::of::<K>())
+    }
+
+    /// Retains only the pairs for which `keep` returns true, preserving the
+    /// order of the retained pairs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use singletons::SingletonMap;
+    ///
+    /// let mut map = SingletonMap::new();
+    /// map.insert::<u8>(1);
+    /// map.insert::<u16>(2);
+    /// map.insert::<u32>(3);
+    ///
+    /// map.retain(|_, value| *value % 2 == 1);
+    /// assert_eq!(map.values().copied().collect::<Vec<_>>(), [1, 3]);
+    /// ```
+    pub fn retain<F>(&mut self, mut keep: F)
+    where
+        F: FnMut(&Type, &mut V) -> bool,
+    {
+        self.0.retain(|key, value| keep(key, value));
+    }
+
+    /// Removes the pairs in the given range of insertion positions,
+    /// returning them as an iterator.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds, or its start is greater than
+    /// its end.
+    pub fn drain<R>(&mut self, range: R) -> Drain<'_, V>
+    where
+        R: RangeBounds<usize>,
+    {
+        Drain(self.0.drain(range))
+    }
+
+    /// Splits the map in two at the given position, returning a map with
+    /// the pairs from `at` onward.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `at > len`.
+    #[must_use = "use `.truncate()` if you don't need the other half"]
+    pub fn split_off(&mut self, at: usize) -> Self {
+        SingletonMap(self.0.split_off(at))
+    }
+
+    /// Shortens the map to its first `len` pairs, dropping the rest.
+    ///
+    /// This has no effect if `len` is greater than the map's current length.
+    pub fn truncate(&mut self, len: usize) {
+        self.0.truncate(len);
+    }
+
+    /// Sorts the pairs with a comparison function, keeping the relative
+    /// order of equal pairs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use singletons::SingletonMap;
+    ///
+    /// let mut map = SingletonMap::new();
+    /// map.insert::<u8>(3);
+    /// map.insert::<u16>(1);
+    /// map.insert::<u32>(2);
+    ///
+    /// map.sort_by(|_, a, _, b| a.cmp(b));
+    /// assert_eq!(map.values().copied().collect::<Vec<_>>(), [1, 2, 3]);
+    /// ```
+    pub fn sort_by<F>(&mut self, mut compare: F)
+    where
+        F: FnMut(&Type, &V, &Type, &V) -> Ordering,
+    {
+        self.0.sort_by(|k1, v1, k2, v2| compare(k1, v1, k2, v2));
+    }
+
+    /// Sorts the pairs with a comparison function, without preserving the
+    /// relative order of equal pairs.
+    pub fn sort_unstable_by<F>(&mut self, mut compare: F)
+    where
+        F: FnMut(&Type, &V, &Type, &V) -> Ordering,
+    {
+        self.0
+            .sort_unstable_by(|k1, v1, k2, v2| compare(k1, v1, k2, v2));
+    }
+
+    /// Sorts the pairs by their type keys, using [`Type`]'s [`Ord`]
+    /// implementation.
+    pub fn sort_keys(&mut self) {
+        self.0.sort_keys();
+    }
+
+    /// Reverses the order of the pairs in place.
+    pub fn reverse(&mut self) {
+        self.0.reverse();
+    }
+
     /// Returns true if the map contains a value for the specified type key `K`.
     ///
     /// # Examples
@@ -199,6 +330,76 @@ impl<V> SingletonMap<V> {
         self.0.contains_key(&Type::of::<K>())
     }
 
+    /// Inserts a value into the map with a runtime [`Type`] as the key.
+    ///
+    /// This behaves like [`.insert()`], for keys that are only known at
+    /// runtime, such as those returned by [`.keys()`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use singletons::{SingletonMap, Type};
+    ///
+    /// let mut map = SingletonMap::new();
+    /// assert_eq!(map.insert_type(Type::of::<u8>(), "byte"), None);
+    /// assert_eq!(map.get::<u8>(), Some(&"byte"));
+    /// ```
+    ///
+    /// [`.insert()`]: Self::insert()
+    /// [`.keys()`]: Self::keys()
+    pub fn insert_type(&mut self, key: Type, value: V) -> Option<V> {
+        self.0.insert(key, value)
+    }
+
+    /// Returns a reference to the value corresponding to a runtime
+    /// [`Type`] key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use singletons::SingletonMap;
+    ///
+    /// let mut map = SingletonMap::new();
+    /// map.insert::<u8>(1);
+    /// map.insert::<u16>(2);
+    ///
+    /// let total: i32 = map.keys().filter_map(|ty| map.get_by_type(ty)).sum();
+    /// assert_eq!(total, 3);
+    /// ```
+    #[must_use]
+    pub fn get_by_type(&self, key: &Type) -> Option<&V> {
+        self.0.get(key)
+    }
+
+    /// Returns a mutable reference to the value corresponding to a runtime
+    /// [`Type`] key.
+    pub fn get_mut_by_type(&mut self, key: &Type) -> Option<&mut V> {
+        self.0.get_mut(key)
+    }
+
+    /// Removes the value corresponding to a runtime [`Type`] key, returning
+    /// it if it was present.
+    pub fn remove_by_type(&mut self, key: &Type) -> Option<V> {
+        self.0.shift_remove(key)
+    }
+
+    /// Returns true if the map contains a value for a runtime [`Type`] key.
+    #[must_use]
+    pub fn contains_type(&self, key: &Type) -> bool {
+        self.0.contains_key(key)
+    }
+
+    /// Returns the key-value pair at a position in insertion order, if it
+    /// exists.
+    pub(crate) fn get_index(&self, index: usize) -> Option<(&Type, &V)> {
+        self.0.get_index(index)
+    }
+
+    /// Gets the entry for a runtime [`Type`] key for in-place manipulation.
+    pub fn entry_by_type(&mut self, key: Type) -> Entry<'_, V> {
+        Entry::new(self.0.entry(key))
+    }
+
     /// Returns an iterator visiting all type keys in insertion order.
     #[must_use]
     pub fn keys(&self) -> Keys<'_, V> {
@@ -239,10 +440,223 @@ impl<V> SingletonMap<V> {
     /// assert_eq!(map.get::<u8>(), Some(&"default".to_string()));
     /// ```
     pub fn entry<K: 'static>(&mut self) -> Entry<'_, V> {
-        Entry {
-            inner: self.0.entry(Type::of::<K>()),
+        Entry::new(self.0.entry(Type::of::<K>()))
+    }
+
+    /// Returns a report of the memory used by the value of each type key,
+    /// in insertion order.
+    ///
+    /// Every entry has the shallow size of `V`. To include the memory owned
+    /// by the values, use [`.deep_memory_report()`].
+    ///
+    /// [`.deep_memory_report()`]: Self::deep_memory_report()
+    #[must_use]
+    pub fn memory_report(&self) -> MemoryReport {
+        self.report_with(|_| None)
+    }
+
+    fn report_with(&self, deep: impl Fn(&V) -> Option<usize>) -> MemoryReport {
+        MemoryReport::new(
+            self.0
+                .iter()
+                .map(|(ty, value)| MemoryUsage::new(*ty, std::mem::size_of::<V>(), deep(value)))
+                .collect(),
+        )
+    }
+}
+
+impl<V> SingletonMap<V> {
+    /// Converts each value with `f`, keeping the same type keys in the same
+    /// order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use singletons::SingletonMap;
+    ///
+    /// let mut ports = SingletonMap::new();
+    /// ports.insert::<u8>(8080u16);
+    /// ports.insert::<u16>(8081u16);
+    ///
+    /// let urls = ports.map_values(|_, port| format!("localhost:{}", port));
+    /// assert_eq!(urls.get::<u16>().map(String::as_str), Some("localhost:8081"));
+    /// ```
+    #[must_use]
+    pub fn map_values<U, F>(self, mut f: F) -> SingletonMap<U>
+    where
+        F: FnMut(&Type, V) -> U,
+    {
+        SingletonMap(
+            self.0
+                .into_iter()
+                .map(|(key, value)| {
+                    let value = f(&key, value);
+                    (key, value)
+                })
+                .collect(),
+        )
+    }
+
+    /// Converts each value with `f`, dropping the pairs for which it
+    /// returns [`None`], and keeping the order of the rest.
+    #[must_use]
+    pub fn filter_map_values<U, F>(self, mut f: F) -> SingletonMap<U>
+    where
+        F: FnMut(&Type, V) -> Option<U>,
+    {
+        SingletonMap(
+            self.0
+                .into_iter()
+                .filter_map(|(key, value)| f(&key, value).map(|value| (key, value)))
+                .collect(),
+        )
+    }
+
+    /// Converts each value with a fallible `f`.
+    ///
+    /// Every value is converted, even after a failure, so that if any
+    /// conversions fail, the error is a map of every failure by type key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use singletons::{SingletonMap, Type};
+    ///
+    /// let mut raw = SingletonMap::new();
+    /// raw.insert::<u8>("1");
+    /// raw.insert::<u16>("two");
+    /// raw.insert::<u32>("3");
+    ///
+    /// let errors = raw.try_map_values(|_, s| s.parse::<u32>()).unwrap_err();
+    /// assert_eq!(errors.keys().copied().collect::<Vec<_>>(), [Type::of::<u16>()]);
+    /// ```
+    pub fn try_map_values<U, E, F>(self, mut f: F) -> Result<SingletonMap<U>, SingletonMap<E>>
+    where
+        F: FnMut(&Type, V) -> Result<U, E>,
+    {
+        let mut values = IndexMap::with_capacity(self.0.len());
+        let mut errors = IndexMap::new();
+        for (key, value) in self.0 {
+            match f(&key, value) {
+                Ok(value) => {
+                    values.insert(key, value);
+                }
+                Err(error) => {
+                    errors.insert(key, error);
+                }
+            }
+        }
+        if errors.is_empty() {
+            Ok(SingletonMap(values))
+        } else {
+            Err(SingletonMap(errors))
         }
     }
+
+    /// Merges another map into this one, combining the values of type keys
+    /// present in both with `f`.
+    ///
+    /// The result has the keys of `self` in their order, followed by the
+    /// keys only in `other` in their order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use singletons::SingletonMap;
+    ///
+    /// let mut defaults = SingletonMap::new();
+    /// defaults.insert::<u8>(1);
+    /// defaults.insert::<u16>(2);
+    ///
+    /// let mut overrides = SingletonMap::new();
+    /// overrides.insert::<u16>(20);
+    /// overrides.insert::<u32>(30);
+    ///
+    /// let merged = defaults.merge_with(overrides, |_, a, b| a + b);
+    /// assert_eq!(merged.values().copied().collect::<Vec<_>>(), [1, 22, 30]);
+    /// ```
+    #[must_use]
+    pub fn merge_with<F>(self, other: Self, mut f: F) -> Self
+    where
+        F: FnMut(&Type, V, V) -> V,
+    {
+        let mut merged = self.0;
+        for (key, value) in other.0 {
+            match merged.entry(key) {
+                indexmap::map::Entry::Occupied(entry) => {
+                    // Swap removal moves the last entry into `index`, so
+                    // swapping the combined entry back restores the order.
+                    let index = entry.index();
+                    let current = entry.swap_remove();
+                    let (last, _) = merged.insert_full(key, f(&key, current, value));
+                    merged.swap_indices(index, last);
+                }
+                indexmap::map::Entry::Vacant(entry) => {
+                    entry.insert(value);
+                }
+            }
+        }
+        SingletonMap(merged)
+    }
+
+    /// Pairs up the values of type keys present in both maps, in the order
+    /// of `self`, dropping the keys present in only one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use singletons::SingletonMap;
+    ///
+    /// let mut names = SingletonMap::new();
+    /// names.insert::<u8>("byte");
+    /// names.insert::<u16>("word");
+    ///
+    /// let mut sizes = SingletonMap::new();
+    /// sizes.insert::<u16>(2);
+    /// sizes.insert::<u8>(1);
+    /// sizes.insert::<u32>(4);
+    ///
+    /// let zipped = names.zip(sizes);
+    /// assert_eq!(zipped.values().copied().collect::<Vec<_>>(), [("byte", 1), ("word", 2)]);
+    /// ```
+    #[must_use]
+    pub fn zip<U>(self, other: SingletonMap<U>) -> SingletonMap<(V, U)> {
+        let mut other = other.0;
+        SingletonMap(
+            self.0
+                .into_iter()
+                .filter_map(|(key, value)| {
+                    other
+                        .swap_remove(&key)
+                        .map(|other_value| (key, (value, other_value)))
+                })
+                .collect(),
+        )
+    }
+}
+
+impl<V: DeepSize> SingletonMap<V> {
+    /// Returns a report of the memory used by the value of each type key,
+    /// including the memory owned by each value as reported by
+    /// [`DeepSize`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use singletons::SingletonMap;
+    ///
+    /// let mut map = SingletonMap::new();
+    /// map.insert::<u8>(String::with_capacity(8));
+    /// map.insert::<u16>(String::with_capacity(16));
+    ///
+    /// let mut report = map.deep_memory_report();
+    /// report.sort_by_size();
+    /// assert_eq!(report.iter().next().unwrap().ty().as_name(), "u16");
+    /// ```
+    #[must_use]
+    pub fn deep_memory_report(&self) -> MemoryReport {
+        self.report_with(|value| Some(value.deep_size()))
+    }
 }
 
 impl<V: Default> SingletonMap<V> {
@@ -263,7 +677,33 @@ impl<V: Default> SingletonMap<V> {
     }
 }
 
-impl<V: Default> Default for SingletonMap<V> {
+impl<V> Index<&Type> for SingletonMap<V> {
+    type Output = V;
+
+    /// Returns a reference to the value for a runtime [`Type`] key.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the key is not present in the map.
+    fn index(&self, key: &Type) -> &V {
+        self.get_by_type(key)
+            .unwrap_or_else(|| panic!("no entry found for type `{}`", key.as_name()))
+    }
+}
+
+impl<V> IndexMut<&Type> for SingletonMap<V> {
+    /// Returns a mutable reference to the value for a runtime [`Type`] key.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the key is not present in the map.
+    fn index_mut(&mut self, key: &Type) -> &mut V {
+        self.get_mut_by_type(key)
+            .unwrap_or_else(|| panic!("no entry found for type `{}`", key.as_name()))
+    }
+}
+
+impl<V> Default for SingletonMap<V> {
     fn default() -> Self {
         Self::new()
     }
@@ -275,6 +715,77 @@ impl<V: Clone> Clone for SingletonMap<V> {
     }
 }
 
+/// Maps are equal if they have the same keys with equal values, regardless
+/// of insertion order.
+impl<V: PartialEq> PartialEq for SingletonMap<V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<V: Eq> Eq for SingletonMap<V> {}
+
+/// The hash does not depend on insertion order, which agrees with the
+/// [`PartialEq`] implementation.
+impl<V: Hash> Hash for SingletonMap<V> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // Each entry is hashed on its own with a fixed hasher, and the
+        // results are combined with a commutative operation.
+        let combined = self.0.iter().fold(0u64, |combined, entry| {
+            let mut hasher = DefaultHasher::new();
+            entry.hash(&mut hasher);
+            combined.wrapping_add(hasher.finish())
+        });
+        state.write_usize(self.0.len());
+        state.write_u64(combined);
+    }
+}
+
+impl<V> FromIterator<(Type, V)> for SingletonMap<V> {
+    /// Creates a map from type keys and values.
+    ///
+    /// If a type key occurs more than once, the last value is kept, at the
+    /// position of the first occurrence.
+    fn from_iter<I: IntoIterator<Item = (Type, V)>>(iter: I) -> Self {
+        SingletonMap(IndexMap::from_iter(iter))
+    }
+}
+
+impl<V> Extend<(Type, V)> for SingletonMap<V> {
+    /// Inserts each type key and value, as with
+    /// [`SingletonMap::insert_type()`].
+    fn extend<I: IntoIterator<Item = (Type, V)>>(&mut self, iter: I) {
+        self.0.extend(iter);
+    }
+}
+
+impl<V> IntoIterator for SingletonMap<V> {
+    type Item = (Type, V);
+    type IntoIter = IntoIter<V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter(self.0.into_iter())
+    }
+}
+
+impl<'a, V> IntoIterator for &'a SingletonMap<V> {
+    type Item = (&'a Type, &'a V);
+    type IntoIter = Iter<'a, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, V> IntoIterator for &'a mut SingletonMap<V> {
+    type Item = (&'a Type, &'a mut V);
+    type IntoIter = IterMut<'a, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
 /// An iterator over the type keys of a `SingletonMap`.
 #[derive(Clone)]
 pub struct Keys<'a, V>(indexmap::map::Keys<'a, Type, V>);
@@ -413,30 +924,150 @@ impl<'a, V> DoubleEndedIterator for IterMut<'a, V> {
     }
 }
 
+/// A draining iterator over a range of the key-value pairs of a
+/// `SingletonMap`.
+///
+/// Created by [`SingletonMap::drain()`].
+pub struct Drain<'a, V>(indexmap::map::Drain<'a, Type, V>);
+
+impl<'a, V> Iterator for Drain<'a, V> {
+    type Item = (Type, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<'a, V> ExactSizeIterator for Drain<'a, V> {
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl<'a, V> DoubleEndedIterator for Drain<'a, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back()
+    }
+}
+
+/// An owning iterator over the key-value pairs of a `SingletonMap`.
+pub struct IntoIter<V>(indexmap::map::IntoIter<Type, V>);
+
+impl<V> Iterator for IntoIter<V> {
+    type Item = (Type, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<V> ExactSizeIterator for IntoIter<V> {
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl<V> DoubleEndedIterator for IntoIter<V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back()
+    }
+}
+
 /// A view into a single entry in a map, which may either be vacant or occupied.
-pub struct Entry<'a, V> {
-    inner: indexmap::map::Entry<'a, Type, V>,
+///
+/// Created by [`SingletonMap::entry()`] and [`SingletonMap::entry_by_type()`].
+#[derive(Debug)]
+pub enum Entry<'a, V> {
+    /// An existing entry.
+    Occupied(OccupiedEntry<'a, V>),
+    /// A vacant entry.
+    Vacant(VacantEntry<'a, V>),
 }
 
 impl<'a, V> Entry<'a, V> {
+    fn new(inner: indexmap::map::Entry<'a, Type, V>) -> Self {
+        match inner {
+            indexmap::map::Entry::Occupied(inner) => Entry::Occupied(OccupiedEntry(inner)),
+            indexmap::map::Entry::Vacant(inner) => Entry::Vacant(VacantEntry(inner)),
+        }
+    }
+
+    /// Returns the type key of the entry.
+    #[must_use]
+    pub fn key(&self) -> &Type {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    /// Returns the index of the entry, whether it exists already or would
+    /// be inserted.
+    #[must_use]
+    pub fn index(&self) -> usize {
+        match self {
+            Entry::Occupied(entry) => entry.index(),
+            Entry::Vacant(entry) => entry.index(),
+        }
+    }
+
     /// Ensures a value is in the entry by inserting the default if empty,
     /// and returns a mutable reference to the value in the entry.
     pub fn or_insert(self, default: V) -> &'a mut V {
-        self.inner.or_insert(default)
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default),
+        }
     }
 
     /// Ensures a value is in the entry by inserting the result of the default
     /// function if empty, and returns a mutable reference to the value in the entry.
     pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'a mut V {
-        self.inner.or_insert_with(default)
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting the result of the default
+    /// function, which is given the type key, if empty, and returns a mutable
+    /// reference to the value in the entry.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use singletons::SingletonMap;
+    ///
+    /// let mut map = SingletonMap::new();
+    /// map.entry::<u8>().or_insert_with_key(|ty| ty.size_of());
+    /// assert_eq!(map.get::<u8>(), Some(&1));
+    /// ```
+    pub fn or_insert_with_key<F: FnOnce(&Type) -> V>(self, default: F) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let value = default(entry.key());
+                entry.insert(value)
+            }
+        }
     }
 
     /// Provides in-place mutable access to an occupied entry before any
     /// potential inserts into the map.
-    pub fn and_modify<F: FnOnce(&mut V)>(self, f: F) -> Self {
-        Entry {
-            inner: self.inner.and_modify(f),
+    #[must_use]
+    pub fn and_modify<F: FnOnce(&mut V)>(mut self, f: F) -> Self {
+        if let Entry::Occupied(entry) = &mut self {
+            f(entry.get_mut());
         }
+        self
     }
 }
 
@@ -444,7 +1075,113 @@ impl<'a, V: Default> Entry<'a, V> {
     /// Ensures a value is in the entry by inserting the default value if empty,
     /// and returns a mutable reference to the value in the entry.
     pub fn or_default(self) -> &'a mut V {
-        self.inner.or_default()
+        self.or_insert_with(V::default)
+    }
+}
+
+/// A view into an occupied entry in a [`SingletonMap`].
+///
+/// It is part of the [`Entry`] enum.
+#[derive(Debug)]
+pub struct OccupiedEntry<'a, V>(indexmap::map::OccupiedEntry<'a, Type, V>);
+
+impl<'a, V> OccupiedEntry<'a, V> {
+    /// Returns the type key of the entry.
+    #[must_use]
+    pub fn key(&self) -> &Type {
+        self.0.key()
+    }
+
+    /// Returns the index of the entry in the map's insertion order.
+    #[must_use]
+    pub fn index(&self) -> usize {
+        self.0.index()
+    }
+
+    /// Returns a reference to the value in the entry.
+    #[must_use]
+    pub fn get(&self) -> &V {
+        self.0.get()
+    }
+
+    /// Returns a mutable reference to the value in the entry.
+    ///
+    /// To get a reference that outlives the entry, use [`.into_mut()`].
+    ///
+    /// [`.into_mut()`]: Self::into_mut()
+    pub fn get_mut(&mut self) -> &mut V {
+        self.0.get_mut()
+    }
+
+    /// Converts the entry into a mutable reference to its value, with the
+    /// lifetime of the map.
+    #[must_use]
+    pub fn into_mut(self) -> &'a mut V {
+        self.0.into_mut()
+    }
+
+    /// Replaces the value in the entry, returning the old value.
+    pub fn insert(&mut self, value: V) -> V {
+        self.0.insert(value)
+    }
+
+    /// Removes the entry from the map, returning its value.
+    ///
+    /// Like [`SingletonMap::remove()`], this preserves the order of the
+    /// remaining entries, as [`.shift_remove()`] does.
+    ///
+    /// [`.shift_remove()`]: Self::shift_remove()
+    pub fn remove(self) -> V {
+        self.shift_remove()
+    }
+
+    /// Removes the entry from the map by swapping it with the last entry,
+    /// returning its value.
+    ///
+    /// This takes constant time, but changes the position of the last entry.
+    pub fn swap_remove(self) -> V {
+        self.0.swap_remove()
+    }
+
+    /// Removes the entry from the map by shifting all of the following
+    /// entries, returning its value.
+    ///
+    /// This preserves the order of the remaining entries, but takes linear
+    /// time.
+    pub fn shift_remove(self) -> V {
+        self.0.shift_remove()
+    }
+}
+
+/// A view into a vacant entry in a [`SingletonMap`].
+///
+/// It is part of the [`Entry`] enum.
+#[derive(Debug)]
+pub struct VacantEntry<'a, V>(indexmap::map::VacantEntry<'a, Type, V>);
+
+impl<'a, V> VacantEntry<'a, V> {
+    /// Returns the type key that would be used when inserting a value.
+    #[must_use]
+    pub fn key(&self) -> &Type {
+        self.0.key()
+    }
+
+    /// Takes ownership of the type key.
+    #[must_use]
+    pub fn into_key(self) -> Type {
+        self.0.into_key()
+    }
+
+    /// Returns the index the entry would have once inserted, which is the
+    /// end of the map.
+    #[must_use]
+    pub fn index(&self) -> usize {
+        self.0.index()
+    }
+
+    /// Inserts a value into the entry, returning a mutable reference to it.
+    pub fn insert(self, value: V) -> &'a mut V {
+        self.0.insert(value)
     }
 }
 
@@ -522,6 +1259,173 @@ mod tests {
         assert_eq!(map.get::<u8>(), Some(&"default modified".to_string()));
     }
 
+    #[test]
+    fn test_singletonmap_by_type() {
+        let mut map = SingletonMap::new();
+        let key = Type::of::<u8>();
+
+        assert_eq!(map.insert_type(key, 1), None);
+        assert!(map.contains_type(&key));
+        *map.entry_by_type(Type::of::<u16>()).or_insert(10) += 1;
+
+        let keys: Vec<Type> = map.keys().copied().collect();
+        for key in &keys {
+            map[key] *= 2;
+        }
+        assert_eq!(map[&key], 2);
+        assert_eq!(map.get_by_type(&Type::of::<u16>()), Some(&22));
+
+        *map.get_mut_by_type(&key).unwrap() += 1;
+        assert_eq!(map.remove_by_type(&key), Some(3));
+        assert_eq!(map.get_by_type(&key), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "no entry found for type `u32`")]
+    fn test_singletonmap_index_missing() {
+        let map: SingletonMap<u8> = SingletonMap::new();
+        let _ = map[&Type::of::<u32>()];
+    }
+
+    #[test]
+    fn test_singletonmap_entry_variants() {
+        let mut map = SingletonMap::new();
+        map.insert::<u8>("eight".to_string());
+        map.insert::<u16>("sixteen".to_string());
+        map.insert::<u32>("thirty-two".to_string());
+
+        match map.entry::<u64>() {
+            Entry::Vacant(entry) => {
+                assert_eq!(entry.key(), &Type::of::<u64>());
+                assert_eq!(entry.index(), 3);
+                entry.insert("sixty-four".to_string());
+            }
+            Entry::Occupied(_) => unreachable!(),
+        }
+
+        match map.entry::<u8>() {
+            Entry::Occupied(mut entry) => {
+                assert_eq!(entry.index(), 0);
+                assert_eq!(entry.insert("8".to_string()), "eight");
+                entry.get_mut().push('!');
+                assert_eq!(entry.get(), "8!");
+                assert_eq!(entry.remove(), "8!");
+            }
+            Entry::Vacant(_) => unreachable!(),
+        }
+        assert_eq!(
+            map.values().map(String::as_str).collect::<Vec<_>>(),
+            ["sixteen", "thirty-two", "sixty-four"]
+        );
+
+        if let Entry::Occupied(entry) = map.entry::<u16>() {
+            assert_eq!(entry.swap_remove(), "sixteen");
+        }
+        assert_eq!(
+            map.values().map(String::as_str).collect::<Vec<_>>(),
+            ["sixty-four", "thirty-two"]
+        );
+
+        let key = map.entry::<i8>().key().to_name();
+        assert_eq!(map.entry::<i8>().or_insert_with_key(Type::to_name), &key);
+    }
+
+    #[test]
+    fn test_singletonmap_collection_traits() {
+        fn hash_of<T: Hash>(value: &T) -> u64 {
+            let mut hasher = DefaultHasher::new();
+            value.hash(&mut hasher);
+            hasher.finish()
+        }
+
+        let forward: SingletonMap<u8> = [(Type::of::<u8>(), 1), (Type::of::<u16>(), 2)]
+            .into_iter()
+            .collect();
+        let mut backward = SingletonMap::default();
+        backward.extend([(Type::of::<u16>(), 2), (Type::of::<u8>(), 1)]);
+
+        assert_eq!(forward, backward);
+        assert_eq!(hash_of(&forward), hash_of(&backward));
+
+        for (_, value) in &mut backward {
+            *value += 1;
+        }
+        assert_ne!(forward, backward);
+        assert_eq!((&backward).into_iter().map(|(_, v)| v).sum::<u8>(), 5);
+
+        let pairs: Vec<(Type, u8)> = forward.into_iter().collect();
+        assert_eq!(pairs, [(Type::of::<u8>(), 1), (Type::of::<u16>(), 2)]);
+    }
+
+    #[test]
+    fn test_singletonmap_bulk_mutation() {
+        let mut map: SingletonMap<u8> = [
+            (Type::of::<u8>(), 1),
+            (Type::of::<u16>(), 2),
+            (Type::of::<u32>(), 3),
+            (Type::of::<u64>(), 4),
+            (Type::of::<u128>(), 5),
+        ]
+        .into_iter()
+        .collect();
+
+        let drained: Vec<u8> = map.drain(1..3).map(|(_, v)| v).collect();
+        assert_eq!(drained, [2, 3]);
+
+        let tail = map.split_off(2);
+        assert_eq!(tail.values().copied().collect::<Vec<_>>(), [5]);
+
+        map.extend(tail);
+        map.reverse();
+        assert_eq!(map.values().copied().collect::<Vec<_>>(), [5, 4, 1]);
+
+        map.sort_unstable_by(|_, a, _, b| a.cmp(b));
+        assert_eq!(map.values().copied().collect::<Vec<_>>(), [1, 4, 5]);
+
+        map.sort_keys();
+        assert_eq!(map.values().copied().collect::<Vec<_>>(), [5, 4, 1]);
+
+        map.truncate(2);
+        assert_eq!(map.swap_remove::<u128>(), Some(5));
+        assert_eq!(map.keys().copied().collect::<Vec<_>>(), [Type::of::<u64>()]);
+    }
+
+    #[test]
+    fn test_singletonmap_transformations() {
+        let mut map = SingletonMap::new();
+        map.insert::<u8>(1u32);
+        map.insert::<u16>(2);
+        map.insert::<u32>(3);
+
+        let odd = map
+            .clone()
+            .filter_map_values(|_, v| (v % 2 == 1).then(|| v * 10));
+        assert_eq!(odd.values().copied().collect::<Vec<_>>(), [10, 30]);
+        assert_eq!(odd.keys().nth(1), Some(&Type::of::<u32>()));
+
+        let sizes = map.clone().map_values(|ty, _| ty.size_of());
+        assert_eq!(sizes.values().copied().collect::<Vec<_>>(), [1, 2, 4]);
+
+        let checked = map.clone().try_map_values(|_, v| u8::try_from(v)).unwrap();
+        assert_eq!(checked.get::<u32>(), Some(&3));
+
+        let errors = map
+            .clone()
+            .try_map_values(|_, v| if v > 1 { Err(v) } else { Ok(v) })
+            .unwrap_err();
+        assert_eq!(errors.values().copied().collect::<Vec<_>>(), [2, 3]);
+
+        let mut other = SingletonMap::new();
+        other.insert::<u64>(4);
+        other.insert::<u8>(10);
+        let merged = map.clone().merge_with(other.clone(), |_, a, b| a * b);
+        assert_eq!(merged.values().copied().collect::<Vec<_>>(), [10, 2, 3, 4]);
+
+        let zipped = map.zip(other);
+        assert_eq!(zipped.len(), 1);
+        assert_eq!(zipped.get::<u8>(), Some(&(1, 10)));
+    }
+
     #[test]
     fn test_singletonmap_get_or_insert_default() {
         let mut map: SingletonMap<String> = SingletonMap::new();
@@ -571,4 +1475,18 @@ mod tests {
         let cloned = map.clone();
         assert_eq!(cloned.get::<u8>(), Some(&"value".to_string()));
     }
+
+    #[test]
+    fn test_singletonmap_macro() {
+        let map = crate::singleton_map! {
+            u8 => "byte",
+            String => "text",
+            u8 => "replaced",
+        };
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get::<u8>(), Some(&"replaced"));
+
+        let empty: SingletonMap<&str> = crate::singleton_map! {};
+        assert!(empty.is_empty());
+    }
 }
@@ -0,0 +1,338 @@
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    fmt::{Display, Formatter},
+};
+
+use crate::Type;
+
+/// A type that can report how much memory it owns beyond its own size.
+///
+/// The shallow size of every value is already known from its [`Type`]. This
+/// trait reports the rest, such as the heap allocations of a `String` or a
+/// `Vec`, so that memory reports can attribute them to the owning type.
+///
+/// # Example
+///
+/// ```
+/// use singletons::DeepSize;
+///
+/// struct Cache {
+///     entries: Vec<String>,
+/// }
+///
+/// impl DeepSize for Cache {
+///     fn deep_size(&self) -> usize {
+///         self.entries.deep_size()
+///     }
+/// }
+/// ```
+pub trait DeepSize {
+    /// Returns the number of bytes owned by the value, not including
+    /// `size_of::<Self>()`.
+    fn deep_size(&self) -> usize;
+}
+
+macro_rules! impl_deep_size_for_plain_types {
+    ($($t:ty),* $(,)?) => {
+        $(
+            impl DeepSize for $t {
+                #[inline]
+                fn deep_size(&self) -> usize {
+                    0
+                }
+            }
+        )*
+    };
+}
+
+impl_deep_size_for_plain_types!(
+    (),
+    bool,
+    char,
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    usize,
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    isize,
+    f32,
+    f64,
+    &'static str,
+);
+
+impl DeepSize for String {
+    fn deep_size(&self) -> usize {
+        self.capacity()
+    }
+}
+
+impl<T: DeepSize> DeepSize for Option<T> {
+    fn deep_size(&self) -> usize {
+        self.as_ref().map_or(0, T::deep_size)
+    }
+}
+
+impl<T: DeepSize> DeepSize for Box<T> {
+    fn deep_size(&self) -> usize {
+        std::mem::size_of::<T>() + (**self).deep_size()
+    }
+}
+
+impl<T: DeepSize> DeepSize for Vec<T> {
+    fn deep_size(&self) -> usize {
+        self.capacity() * std::mem::size_of::<T>() + self.iter().map(T::deep_size).sum::<usize>()
+    }
+}
+
+impl<T: DeepSize> DeepSize for VecDeque<T> {
+    fn deep_size(&self) -> usize {
+        self.capacity() * std::mem::size_of::<T>() + self.iter().map(T::deep_size).sum::<usize>()
+    }
+}
+
+impl<K: DeepSize, V: DeepSize, S> DeepSize for HashMap<K, V, S> {
+    fn deep_size(&self) -> usize {
+        // The table layout is an implementation detail of the standard
+        // library, so this is an estimate that ignores control bytes.
+        self.capacity() * std::mem::size_of::<(K, V)>()
+            + self
+                .iter()
+                .map(|(k, v)| k.deep_size() + v.deep_size())
+                .sum::<usize>()
+    }
+}
+
+impl<T: DeepSize, S> DeepSize for HashSet<T, S> {
+    fn deep_size(&self) -> usize {
+        // See the note on `HashMap`.
+        self.capacity() * std::mem::size_of::<T>() + self.iter().map(T::deep_size).sum::<usize>()
+    }
+}
+
+impl<K: DeepSize, V: DeepSize> DeepSize for BTreeMap<K, V> {
+    fn deep_size(&self) -> usize {
+        // Node overhead is not exposed, so only the entries are counted.
+        self.len() * std::mem::size_of::<(K, V)>()
+            + self
+                .iter()
+                .map(|(k, v)| k.deep_size() + v.deep_size())
+                .sum::<usize>()
+    }
+}
+
+/// The memory used by the value associated with a single [`Type`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryUsage {
+    ty: Type,
+    shallow: usize,
+    deep: Option<usize>,
+}
+
+impl MemoryUsage {
+    pub(crate) fn new(ty: Type, shallow: usize, deep: Option<usize>) -> Self {
+        MemoryUsage { ty, shallow, deep }
+    }
+
+    /// Returns the [`Type`] this usage is reported for.
+    pub fn ty(&self) -> &Type {
+        &self.ty
+    }
+
+    /// Returns the size of the value itself in bytes.
+    pub fn shallow_size(&self) -> usize {
+        self.shallow
+    }
+
+    /// Returns the number of bytes owned by the value, if it was measured
+    /// with [`DeepSize`].
+    pub fn deep_size(&self) -> Option<usize> {
+        self.deep
+    }
+
+    /// Returns the sum of the shallow and deep sizes in bytes.
+    pub fn total_size(&self) -> usize {
+        self.shallow + self.deep.unwrap_or(0)
+    }
+}
+
+/// A report of the memory used by each [`Type`] in a collection.
+///
+/// Entries start out in the collection's insertion order and can be sorted
+/// with [`.sort_by_size()`], [`.sort_by_name()`], or [`.sort_by()`]. The
+/// [`Display`] implementation renders the report as a table labelled with
+/// [`Type::as_name()`].
+///
+/// [`.sort_by_size()`]: Self::sort_by_size()
+/// [`.sort_by_name()`]: Self::sort_by_name()
+/// [`.sort_by()`]: Self::sort_by()
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MemoryReport(Vec<MemoryUsage>);
+
+impl MemoryReport {
+    pub(crate) fn new(entries: Vec<MemoryUsage>) -> Self {
+        MemoryReport(entries)
+    }
+
+    /// Returns the number of types in the report.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns true if the report contains no types.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the sum of the total sizes of every entry.
+    #[must_use]
+    pub fn total_size(&self) -> usize {
+        self.0.iter().map(MemoryUsage::total_size).sum()
+    }
+
+    /// Returns the usage reported for the given type, if present.
+    #[must_use]
+    pub fn get(&self, ty: &Type) -> Option<&MemoryUsage> {
+        self.0.iter().find(|usage| usage.ty == *ty)
+    }
+
+    /// Returns an iterator over the entries of the report.
+    pub fn iter(&self) -> std::slice::Iter<'_, MemoryUsage> {
+        self.0.iter()
+    }
+
+    /// Sorts the entries so the largest total size comes first.
+    pub fn sort_by_size(&mut self) {
+        self.0.sort_by_key(|usage| Reverse(usage.total_size()));
+    }
+
+    /// Sorts the entries by the short name of their type, which is the
+    /// label printed by the [`Display`] implementation.
+    ///
+    /// Entries with the same short name are ordered by their full name.
+    pub fn sort_by_name(&mut self) {
+        self.0.sort_by(|a, b| {
+            a.ty.as_name()
+                .cmp(b.ty.as_name())
+                .then_with(|| a.ty.as_str().cmp(b.ty.as_str()))
+        });
+    }
+
+    /// Sorts the entries with a comparator function.
+    pub fn sort_by<F>(&mut self, compare: F)
+    where
+        F: FnMut(&MemoryUsage, &MemoryUsage) -> std::cmp::Ordering,
+    {
+        self.0.sort_by(compare);
+    }
+}
+
+impl Display for MemoryReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let width = self
+            .0
+            .iter()
+            .map(|usage| usage.ty.as_name().len())
+            .chain(Some("type".len()))
+            .max()
+            .unwrap_or_default();
+
+        writeln!(
+            f,
+            "{:<width$} {:>10} {:>10} {:>10}",
+            "type",
+            "shallow",
+            "deep",
+            "total",
+            width = width
+        )?;
+        for usage in &self.0 {
+            let deep = usage
+                .deep
+                .map_or_else(|| "-".to_string(), |deep| deep.to_string());
+            writeln!(
+                f,
+                "{:<width$} {:>10} {:>10} {:>10}",
+                usage.ty.as_name(),
+                usage.shallow,
+                deep,
+                usage.total_size(),
+                width = width
+            )?;
+        }
+        write!(
+            f,
+            "{:<width$} {:>10} {:>10} {:>10}",
+            "",
+            "",
+            "",
+            self.total_size(),
+            width = width
+        )
+    }
+}
+
+impl<'a> IntoIterator for &'a MemoryReport {
+    type Item = &'a MemoryUsage;
+    type IntoIter = std::slice::Iter<'a, MemoryUsage>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl IntoIterator for MemoryReport {
+    type Item = MemoryUsage;
+    type IntoIter = std::vec::IntoIter<MemoryUsage>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deep_size_counts_owned_allocations() {
+        assert_eq!(5u32.deep_size(), 0);
+        assert_eq!(String::with_capacity(10).deep_size(), 10);
+
+        let mut strings = Vec::with_capacity(4);
+        strings.push(String::with_capacity(3));
+        assert_eq!(strings.deep_size(), 4 * std::mem::size_of::<String>() + 3);
+    }
+
+    struct Alpha;
+
+    #[test]
+    fn memory_report_sorts_and_totals() {
+        let mut report = MemoryReport::new(vec![
+            MemoryUsage::new(Type::of::<Alpha>(), 0, None),
+            MemoryUsage::new(Type::of::<u8>(), 50, None),
+            MemoryUsage::new(Type::of::<String>(), 24, Some(100)),
+            MemoryUsage::new(Type::of::<u64>(), 8, None),
+        ]);
+
+        assert_eq!(report.total_size(), 182);
+
+        report.sort_by_size();
+        let names: Vec<_> = report.iter().map(|usage| usage.ty().as_name()).collect();
+        assert_eq!(names, ["String", "u8", "u64", "Alpha"]);
+
+        report.sort_by_name();
+        let names: Vec<_> = report.iter().map(|usage| usage.ty().as_name()).collect();
+        assert_eq!(names, ["Alpha", "String", "u64", "u8"]);
+
+        assert!(report.to_string().contains("String"));
+    }
+}
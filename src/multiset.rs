@@ -0,0 +1,309 @@
+use std::{
+    any::Any,
+    fmt::{Debug, Formatter},
+};
+
+use indexmap::IndexMap;
+
+use crate::Type;
+
+/// A set that stores any number of values of each type.
+///
+/// This is the multi-instance sibling of [`SingletonSet`]: each [`Type`]
+/// maps to a list of values of that type, in the order they were pushed.
+/// Unlike storing a `Vec<T>` in a [`SingletonSet`], "a list of `T`" and "a
+/// single `Vec<T>`" are distinct, and the values of each type can be
+/// counted, iterated, and filtered directly.
+///
+/// A type is present in the set as long as it has at least one value, so
+/// [`.types()`] only visits types with values.
+///
+/// # Example
+///
+/// ```
+/// use singletons::SingletonMultiSet;
+///
+/// struct Listener(&'static str);
+///
+/// let mut set = SingletonMultiSet::new();
+/// set.push(Listener("audio"));
+/// set.push(Listener("network"));
+/// set.push(42u32);
+///
+/// assert_eq!(set.count::<Listener>(), 2);
+/// let names: Vec<_> = set.iter::<Listener>().map(|l| l.0).collect();
+/// assert_eq!(names, ["audio", "network"]);
+///
+/// let removed = set.remove_where::<Listener>(|l| l.0 == "audio");
+/// assert_eq!(removed.len(), 1);
+/// assert_eq!(set.count::<Listener>(), 1);
+/// ```
+///
+/// [`SingletonSet`]: crate::SingletonSet
+/// [`.types()`]: Self::types()
+#[derive(Default)]
+pub struct SingletonMultiSet(IndexMap<Type, Box<dyn Any>>);
+
+impl SingletonMultiSet {
+    /// Creates an empty `SingletonMultiSet`.
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        SingletonMultiSet(IndexMap::new())
+    }
+
+    /// Creates an empty `SingletonMultiSet` with room for at least the
+    /// specified number of types.
+    #[inline]
+    #[must_use]
+    pub fn with_capacity(capacity: usize) -> Self {
+        SingletonMultiSet(IndexMap::with_capacity(capacity))
+    }
+
+    /// Returns the number of types in the set.
+    #[inline]
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns true if the set contains no values.
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Removes all values from the set.
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    /// Returns true if the set contains at least one value of the type.
+    #[must_use]
+    pub fn contains<T>(&self) -> bool
+    where
+        T: 'static,
+    {
+        self.0.contains_key(&Type::of::<T>())
+    }
+
+    /// Returns the number of values of the specified type.
+    #[must_use]
+    pub fn count<T>(&self) -> usize
+    where
+        T: 'static,
+    {
+        self.get::<T>().len()
+    }
+
+    /// Appends a value to the list for its type.
+    pub fn push<T>(&mut self, value: T)
+    where
+        T: 'static,
+    {
+        self.0
+            .entry(Type::of::<T>())
+            .or_insert_with(|| Box::new(Vec::<T>::new()))
+            .downcast_mut::<Vec<T>>()
+            .expect("values are stored under their own type")
+            .push(value);
+    }
+
+    /// Returns the values of the specified type as a slice, in the order
+    /// they were pushed.
+    #[must_use]
+    pub fn get<T>(&self) -> &[T]
+    where
+        T: 'static,
+    {
+        self.values::<T>().map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns the values of the specified type as a mutable slice, in the
+    /// order they were pushed.
+    pub fn get_mut<T>(&mut self) -> &mut [T]
+    where
+        T: 'static,
+    {
+        self.0.get_mut(&Type::of::<T>()).map_or(&mut [], |values| {
+            values
+                .downcast_mut::<Vec<T>>()
+                .expect("values are stored under their own type")
+                .as_mut_slice()
+        })
+    }
+
+    /// Returns an iterator over the values of the specified type, in the
+    /// order they were pushed.
+    pub fn iter<T>(&self) -> std::slice::Iter<'_, T>
+    where
+        T: 'static,
+    {
+        self.get::<T>().iter()
+    }
+
+    /// Returns an iterator over mutable references to the values of the
+    /// specified type, in the order they were pushed.
+    pub fn iter_mut<T>(&mut self) -> std::slice::IterMut<'_, T>
+    where
+        T: 'static,
+    {
+        self.get_mut::<T>().iter_mut()
+    }
+
+    /// Removes the values of the specified type for which `predicate`
+    /// returns true, returning them in their original order.
+    ///
+    /// If no values of the type remain, the type is removed from the set.
+    pub fn remove_where<T>(&mut self, mut predicate: impl FnMut(&T) -> bool) -> Vec<T>
+    where
+        T: 'static,
+    {
+        let ty = Type::of::<T>();
+        let values = match self.0.get_mut(&ty) {
+            Some(values) => values
+                .downcast_mut::<Vec<T>>()
+                .expect("values are stored under their own type"),
+            None => return Vec::new(),
+        };
+
+        // The predicate runs before anything is moved, so the values are
+        // left in place if it panics.
+        let matches: Vec<bool> = values.iter().map(&mut predicate).collect();
+        let mut matches = matches.into_iter();
+        let (removed, kept) = values.drain(..).partition(|_| matches.next() == Some(true));
+        *values = kept;
+
+        if values.is_empty() {
+            self.0.shift_remove(&ty);
+        }
+        removed
+    }
+
+    /// Removes every value of the specified type, returning them in the
+    /// order they were pushed.
+    pub fn remove_all<T>(&mut self) -> Vec<T>
+    where
+        T: 'static,
+    {
+        self.0
+            .shift_remove(&Type::of::<T>())
+            .map_or_else(Vec::new, |values| match values.downcast::<Vec<T>>() {
+                Ok(values) => *values,
+                Err(_) => unreachable!("values are stored under their own type"),
+            })
+    }
+
+    /// Returns an iterator that visits each [`Type`] with at least one
+    /// value, in the order each type was first pushed.
+    pub fn types(&self) -> MultiTypes<'_> {
+        MultiTypes(self.0.keys())
+    }
+
+    fn values<T: 'static>(&self) -> Option<&Vec<T>> {
+        self.0.get(&Type::of::<T>()).map(|values| {
+            values
+                .downcast_ref()
+                .expect("values are stored under their own type")
+        })
+    }
+}
+
+impl Debug for SingletonMultiSet {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.0.keys()).finish()
+    }
+}
+
+/// An iterator over the types in a [`SingletonMultiSet`].
+///
+/// Created by [`SingletonMultiSet::types()`].
+#[derive(Clone, Debug)]
+pub struct MultiTypes<'a>(indexmap::map::Keys<'a, Type, Box<dyn Any>>);
+
+impl<'a> Iterator for MultiTypes<'a> {
+    type Item = &'a Type;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<'a> ExactSizeIterator for MultiTypes<'a> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl<'a> DoubleEndedIterator for MultiTypes<'a> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn multiset_keeps_values_per_type() {
+        let mut set = SingletonMultiSet::new();
+        set.push(1u8);
+        set.push("a");
+        set.push(2u8);
+        set.push(vec![3u8]);
+
+        assert_eq!(set.get::<u8>(), [1, 2]);
+        assert_eq!(set.count::<Vec<u8>>(), 1);
+        assert_eq!(set.count::<u16>(), 0);
+        assert_eq!(
+            set.types().copied().collect::<Vec<_>>(),
+            [Type::of::<u8>(), Type::of::<&str>(), Type::of::<Vec<u8>>()]
+        );
+
+        for value in set.iter_mut::<u8>() {
+            *value *= 10;
+        }
+        assert_eq!(set.iter::<u8>().sum::<u8>(), 30);
+    }
+
+    #[test]
+    fn multiset_drops_types_without_values() {
+        let mut set = SingletonMultiSet::new();
+        set.push(1u8);
+        set.push(2u8);
+        set.push("a");
+
+        assert_eq!(set.remove_where::<u8>(|v| *v > 1), [2]);
+        assert!(set.contains::<u8>());
+        assert_eq!(set.remove_where::<u8>(|_| true), [1]);
+        assert!(!set.contains::<u8>());
+        assert!(set.remove_where::<u16>(|_| true).is_empty());
+
+        assert_eq!(set.remove_all::<&str>(), ["a"]);
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    fn multiset_remove_where_keeps_values_if_the_predicate_panics() {
+        let mut set = SingletonMultiSet::new();
+        set.push(1u8);
+        set.push(2u8);
+        set.push(3u8);
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            set.remove_where::<u8>(|v| if *v == 2 { panic!("predicate") } else { true })
+        }));
+        assert!(result.is_err());
+        assert_eq!(set.get::<u8>(), [1, 2, 3]);
+    }
+}
@@ -0,0 +1,265 @@
+use indexmap::IndexMap;
+
+use crate::Type;
+
+/// A map that uses pairs of types as keys and stores values of a single
+/// type `V`.
+///
+/// This is the double-dispatch sibling of [`SingletonMap`]: the value for a
+/// pair `(A, B)` is looked up by both types at once, which suits tables of
+/// collision handlers or conversions between types.
+///
+/// A map created with [`.symmetric()`] treats `(A, B)` and `(B, A)` as the
+/// same key. Each entry keeps the order of the types it was first inserted
+/// with, which [`.get_key_value_by_types()`] reports, so a handler can be
+/// called with its arguments in the order it expects.
+///
+/// # Example
+///
+/// ```
+/// use singletons::SingletonPairMap;
+///
+/// struct Circle;
+/// struct Square;
+///
+/// let mut handlers = SingletonPairMap::symmetric();
+/// handlers.insert2::<Circle, Square>("circle hits square");
+/// handlers.insert2::<Circle, Circle>("circle hits circle");
+///
+/// assert_eq!(handlers.get2::<Square, Circle>(), Some(&"circle hits square"));
+/// assert_eq!(handlers.get2::<Square, Square>(), None);
+/// ```
+///
+/// [`SingletonMap`]: crate::SingletonMap
+/// [`.symmetric()`]: Self::symmetric()
+/// [`.get_key_value_by_types()`]: Self::get_key_value_by_types()
+#[derive(Clone, Debug)]
+pub struct SingletonPairMap<V> {
+    map: IndexMap<(Type, Type), V>,
+    symmetric: bool,
+}
+
+impl<V> SingletonPairMap<V> {
+    /// Creates an empty `SingletonPairMap`, where `(A, B)` and `(B, A)` are
+    /// distinct keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use singletons::SingletonPairMap;
+    /// let mut map: SingletonPairMap<String> = SingletonPairMap::new();
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        SingletonPairMap {
+            map: IndexMap::new(),
+            symmetric: false,
+        }
+    }
+
+    /// Creates an empty `SingletonPairMap`, where `(A, B)` and `(B, A)` are
+    /// the same key.
+    #[inline]
+    #[must_use]
+    pub fn symmetric() -> Self {
+        SingletonPairMap {
+            map: IndexMap::new(),
+            symmetric: true,
+        }
+    }
+
+    /// Returns true if `(A, B)` and `(B, A)` are the same key in this map.
+    #[inline]
+    #[must_use]
+    pub fn is_symmetric(&self) -> bool {
+        self.symmetric
+    }
+
+    /// Returns the number of elements in the map.
+    #[inline]
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns true if the map contains no elements.
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Removes all elements from the map.
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    /// Inserts a value for the pair of key types, returning the previous
+    /// value, if any.
+    pub fn insert2<A: 'static, B: 'static>(&mut self, value: V) -> Option<V> {
+        self.insert_types(Type::of::<A>(), Type::of::<B>(), value)
+    }
+
+    /// Inserts a value for the pair of runtime key types, returning the
+    /// previous value, if any.
+    ///
+    /// In a symmetric map, replacing the value of an existing entry keeps
+    /// the order of the types it was first inserted with.
+    pub fn insert_types(&mut self, a: Type, b: Type, value: V) -> Option<V> {
+        match self.index_of(&a, &b) {
+            Some(index) => Some(std::mem::replace(&mut self.map[index], value)),
+            None => self.map.insert((a, b), value),
+        }
+    }
+
+    /// Returns true if the map contains a value for the pair of key types.
+    #[must_use]
+    pub fn contains2<A: 'static, B: 'static>(&self) -> bool {
+        self.index_of(&Type::of::<A>(), &Type::of::<B>()).is_some()
+    }
+
+    /// Returns a reference to the value for the pair of key types, if it
+    /// exists.
+    #[must_use]
+    pub fn get2<A: 'static, B: 'static>(&self) -> Option<&V> {
+        self.get_by_types(&Type::of::<A>(), &Type::of::<B>())
+    }
+
+    /// Returns a mutable reference to the value for the pair of key types,
+    /// if it exists.
+    pub fn get2_mut<A: 'static, B: 'static>(&mut self) -> Option<&mut V> {
+        self.get_mut_by_types(&Type::of::<A>(), &Type::of::<B>())
+    }
+
+    /// Returns a reference to the value for the pair of runtime key types,
+    /// if it exists.
+    #[must_use]
+    pub fn get_by_types(&self, a: &Type, b: &Type) -> Option<&V> {
+        self.get_key_value_by_types(a, b).map(|(_, value)| value)
+    }
+
+    /// Returns a mutable reference to the value for the pair of runtime key
+    /// types, if it exists.
+    pub fn get_mut_by_types(&mut self, a: &Type, b: &Type) -> Option<&mut V> {
+        let index = self.index_of(a, b)?;
+        Some(&mut self.map[index])
+    }
+
+    /// Returns the stored key and the value for the pair of runtime key
+    /// types, if it exists.
+    ///
+    /// In a symmetric map, the stored key is in the order the entry was
+    /// inserted with, which may be `(b, a)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use singletons::{SingletonPairMap, Type};
+    ///
+    /// let mut map = SingletonPairMap::symmetric();
+    /// map.insert2::<u8, u16>("widen");
+    ///
+    /// let (key, _) = map.get_key_value_by_types(&Type::of::<u16>(), &Type::of::<u8>()).unwrap();
+    /// assert_eq!(*key, (Type::of::<u8>(), Type::of::<u16>()));
+    /// ```
+    #[must_use]
+    pub fn get_key_value_by_types(&self, a: &Type, b: &Type) -> Option<(&(Type, Type), &V)> {
+        self.index_of(a, b)
+            .and_then(|index| self.map.get_index(index))
+    }
+
+    /// Removes the value for the pair of key types, returning it if it
+    /// existed.
+    pub fn remove2<A: 'static, B: 'static>(&mut self) -> Option<V> {
+        self.remove_by_types(&Type::of::<A>(), &Type::of::<B>())
+    }
+
+    /// Removes the value for the pair of runtime key types, returning it if
+    /// it existed.
+    ///
+    /// This preserves the order of the remaining entries.
+    pub fn remove_by_types(&mut self, a: &Type, b: &Type) -> Option<V> {
+        let index = self.index_of(a, b)?;
+        self.map.shift_remove_index(index).map(|(_, value)| value)
+    }
+
+    /// Returns an iterator over the stored keys and their values, in
+    /// insertion order.
+    pub fn iter(
+        &self,
+    ) -> impl ExactSizeIterator<Item = (&(Type, Type), &V)> + DoubleEndedIterator + '_ {
+        self.map.iter()
+    }
+
+    fn index_of(&self, a: &Type, b: &Type) -> Option<usize> {
+        // `Type` is `Copy`, so building the lookup key is cheap.
+        self.map.get_index_of(&(*a, *b)).or_else(|| {
+            if self.symmetric && a != b {
+                self.map.get_index_of(&(*b, *a))
+            } else {
+                None
+            }
+        })
+    }
+}
+
+impl<V> Default for SingletonPairMap<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Circle;
+    struct Square;
+
+    #[test]
+    fn test_pairmap_keeps_ordered_pairs_apart() {
+        let mut map = SingletonPairMap::new();
+        assert_eq!(map.insert2::<Circle, Square>(1), None);
+        assert_eq!(map.insert2::<Square, Circle>(2), None);
+        assert_eq!(map.insert2::<Circle, Square>(3), Some(1));
+
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get2::<Circle, Square>(), Some(&3));
+        assert_eq!(map.get2::<Square, Circle>(), Some(&2));
+        assert!(!map.contains2::<Circle, Circle>());
+
+        *map.get2_mut::<Square, Circle>().unwrap() += 10;
+        assert_eq!(
+            map.get_by_types(&Type::of::<Square>(), &Type::of::<Circle>()),
+            Some(&12)
+        );
+        assert_eq!(map.remove2::<Circle, Square>(), Some(3));
+        assert_eq!(map.get2::<Circle, Square>(), None);
+    }
+
+    #[test]
+    fn test_pairmap_symmetric_resolves_either_order() {
+        let circle = Type::of::<Circle>();
+        let square = Type::of::<Square>();
+
+        let mut map = SingletonPairMap::symmetric();
+        map.insert2::<Circle, Square>("first");
+        map.insert2::<Circle, Circle>("same");
+        assert_eq!(map.insert2::<Square, Circle>("second"), Some("first"));
+
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get2::<Circle, Square>(), Some(&"second"));
+        assert_eq!(
+            map.get_key_value_by_types(&square, &circle),
+            Some((&(circle, square), &"second"))
+        );
+        assert_eq!(map.get2::<Circle, Circle>(), Some(&"same"));
+
+        assert_eq!(map.remove_by_types(&square, &circle), Some("second"));
+        assert_eq!(
+            map.iter().map(|(key, _)| *key).collect::<Vec<_>>(),
+            [(circle, circle)]
+        );
+    }
+}
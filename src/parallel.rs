@@ -0,0 +1,649 @@
+use std::{
+    fmt::{Display, Formatter},
+    num::NonZeroUsize,
+    panic::{self, AssertUnwindSafe},
+    sync::{mpsc, Mutex, PoisonError},
+    thread,
+};
+
+use crate::{Access, IntoSystem, ParallelParam, Resources, System, SystemError};
+
+/// A schedule that runs systems in parallel when their access allows it.
+///
+/// Systems are grouped into stages. Two systems share a stage only if
+/// neither writes a type the other reads or writes, and each stage runs
+/// its systems at the same time on a pool of workers in a
+/// [`std::thread::scope`]. The stages themselves run one after another.
+///
+/// Systems that conflict run in the order they were added, unless that is
+/// overridden with [`.before()`] and [`.after()`], which order systems by
+/// label. The resulting plan is deterministic, and can be inspected without
+/// running anything with [`.plan()`], or printed with [`.dry_run()`].
+///
+/// # Example
+///
+/// ```
+/// use singletons::{ParallelSchedule, Res, Resources};
+///
+/// struct Time(u32);
+/// struct Position(u32);
+/// struct Velocity(u32);
+///
+/// fn advance(time: &mut Time) {
+///     time.0 += 1;
+/// }
+///
+/// fn accelerate(velocity: &mut Velocity) {
+///     velocity.0 += 2;
+/// }
+///
+/// fn integrate(time: Res<Time>, velocity: Res<Velocity>, position: &mut Position) {
+///     position.0 += time.0 * velocity.0;
+/// }
+///
+/// let mut resources = Resources::new();
+/// resources.insert(Time(0));
+/// resources.insert(Position(0));
+/// resources.insert(Velocity(0));
+///
+/// let mut schedule = ParallelSchedule::new();
+/// schedule.add_system(integrate).after("physics");
+/// schedule.add_system(advance).label("physics");
+/// schedule.add_system(accelerate).label("physics");
+///
+/// let plan = schedule.plan().unwrap();
+/// assert_eq!(plan.stages().len(), 2);
+/// assert_eq!(plan.stages()[0].len(), 2);
+///
+/// schedule.run(&resources).unwrap();
+/// assert_eq!(resources.borrow::<Position>().0, 2);
+/// ```
+///
+/// [`.before()`]: SystemConfig::before()
+/// [`.after()`]: SystemConfig::after()
+/// [`.plan()`]: Self::plan()
+/// [`.dry_run()`]: Self::dry_run()
+#[derive(Default)]
+pub struct ParallelSchedule {
+    systems: Vec<Scheduled>,
+}
+
+/// A system and its ordering constraints.
+struct Scheduled {
+    system: Box<dyn System + Send>,
+    labels: Vec<&'static str>,
+    before: Vec<&'static str>,
+    after: Vec<&'static str>,
+}
+
+impl ParallelSchedule {
+    /// Creates an empty schedule.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of systems in the schedule.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.systems.len()
+    }
+
+    /// Returns true if the schedule contains no systems.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.systems.is_empty()
+    }
+
+    /// Adds a system to the schedule, returning a [`SystemConfig`] to label
+    /// and order it.
+    ///
+    /// Every parameter of the system must only access `Send + Sync` values,
+    /// as described in [`ParallelParam`], since it may run on another
+    /// thread.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the system's parameters conflict with each
+    /// other, as described in [`IntoSystem::into_system()`].
+    pub fn add_system<Params, S>(&mut self, system: S) -> SystemConfig<'_>
+    where
+        Params: ParallelParam,
+        S: IntoSystem<Params>,
+        S::System: Send,
+    {
+        self.systems.push(Scheduled {
+            system: Box::new(system.into_system()),
+            labels: Vec::new(),
+            before: Vec::new(),
+            after: Vec::new(),
+        });
+        SystemConfig(self.systems.last_mut().unwrap())
+    }
+
+    /// Computes the stages the systems would run in, without running them.
+    ///
+    /// This fails if an ordering constraint names a label no system has, or
+    /// if the constraints form a cycle.
+    pub fn plan(&self) -> Result<Plan, ScheduleError> {
+        let stages = self.stages()?;
+        let mut plan = Plan(Vec::new());
+        for (scheduled, &stage) in self.systems.iter().zip(&stages) {
+            if plan.0.len() <= stage {
+                plan.0.resize_with(stage + 1, Vec::new);
+            }
+            plan.0[stage].push(PlannedSystem {
+                name: scheduled.system.name().to_string(),
+                access: scheduled.system.access().clone(),
+            });
+        }
+        Ok(plan)
+    }
+
+    /// Prints the stages the systems would run in to standard output,
+    /// without running them, and returns the [`Plan`].
+    ///
+    /// This fails for the same reasons as [`.plan()`], in which case nothing
+    /// is printed.
+    ///
+    /// [`.plan()`]: Self::plan()
+    pub fn dry_run(&self) -> Result<Plan, ScheduleError> {
+        let plan = self.plan()?;
+        print!("{}", plan);
+        Ok(plan)
+    }
+
+    /// Runs every stage in order, with the systems of each stage running in
+    /// parallel.
+    ///
+    /// The systems run on a fixed set of worker threads, one per available
+    /// core but no more than the largest stage needs, which are started once
+    /// per call and shared by every stage.
+    ///
+    /// If any system cannot borrow its parameters, the remaining systems of
+    /// its stage still run, but later stages do not, and the error of the
+    /// earliest-added failing system is returned.
+    pub fn run(&mut self, resources: &Resources) -> Result<(), ScheduleError> {
+        let stages = self.stages()?;
+        let count = stages.iter().max().map_or(0, |&last| last + 1);
+        let mut buckets: Vec<Vec<&mut Scheduled>> = (0..count).map(|_| Vec::new()).collect();
+        for (scheduled, stage) in self.systems.iter_mut().zip(stages) {
+            buckets[stage].push(scheduled);
+        }
+
+        let widest = buckets.iter().map(Vec::len).max().unwrap_or(0);
+        if widest <= 1 {
+            for scheduled in buckets.into_iter().flatten() {
+                run_one(scheduled, resources)?;
+            }
+            return Ok(());
+        }
+
+        let resources = SharedResources(resources);
+        let (job_sender, jobs) = mpsc::channel::<(usize, &mut Scheduled)>();
+        let (result_sender, results) = mpsc::channel();
+        let jobs = Mutex::new(jobs);
+        thread::scope(|scope| {
+            // Moved into the scope, so the workers stop once it is dropped,
+            // even if a stage fails.
+            let job_sender = job_sender;
+            for _ in 0..worker_count(widest) {
+                let jobs = &jobs;
+                let result_sender = result_sender.clone();
+                scope.spawn(move || loop {
+                    // The guard is dropped at the end of the statement, so
+                    // other workers can take jobs while this one runs.
+                    let job = jobs.lock().unwrap_or_else(PoisonError::into_inner).recv();
+                    let (index, scheduled) = match job {
+                        Ok(job) => job,
+                        Err(_) => break,
+                    };
+                    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                        run_one(scheduled, resources.get())
+                    }));
+                    if result_sender.send((index, result)).is_err() {
+                        break;
+                    }
+                });
+            }
+
+            for bucket in buckets {
+                let len = bucket.len();
+                for job in bucket.into_iter().enumerate() {
+                    job_sender
+                        .send(job)
+                        .expect("workers run until the job sender is dropped");
+                }
+                let mut stage: Vec<Option<_>> = (0..len).map(|_| None).collect();
+                for _ in 0..len {
+                    let (index, result) = results
+                        .recv()
+                        .expect("workers report every job, even if its system panics");
+                    stage[index] = Some(result);
+                }
+
+                let stage: Vec<Result<(), SystemError>> = stage
+                    .into_iter()
+                    .flatten()
+                    .map(|result| result.unwrap_or_else(|payload| panic::resume_unwind(payload)))
+                    .collect();
+                stage.into_iter().collect::<Result<(), _>>()?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Assigns each system to a stage, in insertion order.
+    fn stages(&self) -> Result<Vec<usize>, ScheduleError> {
+        let n = self.systems.len();
+
+        // `preds[i]` lists the systems that must finish before system `i`.
+        let mut preds: Vec<Vec<usize>> = vec![Vec::new(); n];
+        for (i, scheduled) in self.systems.iter().enumerate() {
+            for &label in &scheduled.after {
+                for j in self.labelled(i, label)? {
+                    preds[i].push(j);
+                }
+            }
+            for &label in &scheduled.before {
+                for j in self.labelled(i, label)? {
+                    preds[j].push(i);
+                }
+            }
+        }
+
+        // Visit systems in topological order, preferring earlier-added
+        // systems, so the plan does not depend on anything else.
+        let mut order = Vec::with_capacity(n);
+        let mut remaining: Vec<usize> = preds.iter().map(Vec::len).collect();
+        let mut done = vec![false; n];
+        while order.len() < n {
+            let next = (0..n).find(|&i| !done[i] && remaining[i] == 0);
+            let next = match next {
+                Some(next) => next,
+                None => {
+                    let cycle = (0..n)
+                        .filter(|&i| !done[i])
+                        .map(|i| self.systems[i].system.name().to_string())
+                        .collect();
+                    return Err(ScheduleError::Cycle(cycle));
+                }
+            };
+            done[next] = true;
+            order.push(next);
+            for (i, pred) in preds.iter().enumerate() {
+                remaining[i] -= pred.iter().filter(|&&j| j == next).count();
+            }
+        }
+
+        // Each system runs after its explicit predecessors and after every
+        // conflicting system that was visited before it.
+        let mut stages = vec![0; n];
+        for (position, &i) in order.iter().enumerate() {
+            let access = self.systems[i].system.access();
+            let explicit = preds[i].iter().map(|&j| stages[j] + 1);
+            let conflicting = order[..position]
+                .iter()
+                .filter(|&&j| self.systems[j].system.access().conflicts_with(access))
+                .map(|&j| stages[j] + 1);
+            stages[i] = explicit.chain(conflicting).max().unwrap_or(0);
+        }
+        Ok(stages)
+    }
+
+    /// Returns the systems with `label`, as referenced by system `from`.
+    fn labelled(&self, from: usize, label: &'static str) -> Result<Vec<usize>, ScheduleError> {
+        let matches: Vec<usize> = (0..self.systems.len())
+            .filter(|&j| j != from && self.systems[j].labels.contains(&label))
+            .collect();
+        if matches.is_empty() && !self.systems.iter().any(|s| s.labels.contains(&label)) {
+            return Err(ScheduleError::UnknownLabel {
+                system: self.systems[from].system.name().to_string(),
+                label,
+            });
+        }
+        Ok(matches)
+    }
+}
+
+/// A [`Resources`] reference that can be shared with the worker threads.
+#[derive(Clone, Copy)]
+struct SharedResources<'a>(&'a Resources);
+
+impl<'a> SharedResources<'a> {
+    /// Returns the reference, which moves the whole wrapper into closures
+    /// rather than just the field.
+    fn get(self) -> &'a Resources {
+        self.0
+    }
+}
+
+// SAFETY: `Resources` is only `!Sync` because it may hold values that are
+// not `Send + Sync`. The systems of a `ParallelSchedule` only borrow values
+// of the types their `ParallelParam`s declare, which are `Send + Sync`, the
+// borrow flags are atomic, and nothing can be inserted or removed through a
+// shared reference.
+unsafe impl Send for SharedResources<'_> {}
+
+// SAFETY: as above.
+unsafe impl Sync for SharedResources<'_> {}
+
+/// Returns the number of worker threads for stages of up to `widest`
+/// systems.
+fn worker_count(widest: usize) -> usize {
+    thread::available_parallelism()
+        .map_or(1, NonZeroUsize::get)
+        .min(widest)
+}
+
+fn run_one(scheduled: &mut Scheduled, resources: &Resources) -> Result<(), SystemError> {
+    let system = &mut scheduled.system;
+    system
+        .run(resources)
+        .map_err(|error| SystemError::new(system.name().to_string(), error))
+}
+
+impl std::fmt::Debug for ParallelSchedule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.systems.iter().map(|s| s.system.name()))
+            .finish()
+    }
+}
+
+/// Labels and ordering constraints for a system in a [`ParallelSchedule`].
+///
+/// Created by [`ParallelSchedule::add_system()`].
+pub struct SystemConfig<'a>(&'a mut Scheduled);
+
+impl<'a> SystemConfig<'a> {
+    /// Adds a label that other systems can be ordered against.
+    ///
+    /// Several systems may share a label, in which case an ordering
+    /// constraint on the label applies to all of them.
+    pub fn label(self, label: &'static str) -> Self {
+        self.0.labels.push(label);
+        self
+    }
+
+    /// Runs this system before every system with `label`.
+    pub fn before(self, label: &'static str) -> Self {
+        self.0.before.push(label);
+        self
+    }
+
+    /// Runs this system after every system with `label`.
+    pub fn after(self, label: &'static str) -> Self {
+        self.0.after.push(label);
+        self
+    }
+}
+
+/// The stages a [`ParallelSchedule`] runs in, as computed by
+/// [`ParallelSchedule::plan()`].
+///
+/// The [`Display`] implementation lists each stage with the types each of
+/// its systems reads and writes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Plan(Vec<Vec<PlannedSystem>>);
+
+impl Plan {
+    /// Returns the stages, each listing its systems in insertion order.
+    #[must_use]
+    pub fn stages(&self) -> &[Vec<PlannedSystem>] {
+        &self.0
+    }
+}
+
+impl Display for Plan {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (i, stage) in self.0.iter().enumerate() {
+            writeln!(f, "stage {}:", i)?;
+            for system in stage {
+                writeln!(f, "  {}", system)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A system in a [`Plan`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlannedSystem {
+    name: String,
+    access: Access,
+}
+
+impl PlannedSystem {
+    /// Returns the name of the system.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the types the system reads and writes.
+    #[must_use]
+    pub fn access(&self) -> &Access {
+        &self.access
+    }
+}
+
+impl Display for PlannedSystem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.name)?;
+        for (verb, types) in [
+            ("reads", self.access.reads()),
+            ("writes", self.access.writes()),
+        ] {
+            if types.is_empty() {
+                continue;
+            }
+            write!(f, " [{}", verb)?;
+            for (i, ty) in types.iter().enumerate() {
+                f.write_str(if i == 0 { " " } else { ", " })?;
+                f.write_str(ty.as_name())?;
+            }
+            f.write_str("]")?;
+        }
+        Ok(())
+    }
+}
+
+/// The error returned when a [`ParallelSchedule`] cannot be planned or run.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ScheduleError {
+    /// A system is ordered against a label that no system has.
+    UnknownLabel {
+        /// The name of the system with the constraint.
+        system: String,
+        /// The unknown label.
+        label: &'static str,
+    },
+    /// The ordering constraints form a cycle among these systems.
+    Cycle(Vec<String>),
+    /// A system could not run.
+    System(SystemError),
+}
+
+impl Display for ScheduleError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScheduleError::UnknownLabel { system, label } => write!(
+                f,
+                "system `{}` is ordered against unknown label `{}`",
+                system, label
+            ),
+            ScheduleError::Cycle(systems) => {
+                write!(
+                    f,
+                    "ordering constraints form a cycle among: {}",
+                    systems.join(", ")
+                )
+            }
+            ScheduleError::System(error) => error.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ScheduleError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ScheduleError::System(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<SystemError> for ScheduleError {
+    fn from(error: SystemError) -> Self {
+        ScheduleError::System(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Barrier};
+
+    use super::*;
+    use crate::{BorrowError, Res, Type};
+
+    struct A(u32);
+    struct B(u32);
+    struct C(u32);
+
+    fn write_a(a: &mut A) {
+        a.0 += 1;
+    }
+    fn write_b(b: &mut B) {
+        b.0 += 1;
+    }
+    fn read_a_write_c(a: Res<A>, c: &mut C) {
+        c.0 += a.0;
+    }
+    fn read_a(_: &A) {}
+
+    fn resources() -> Resources {
+        let mut resources = Resources::new();
+        resources.insert(A(0));
+        resources.insert(B(0));
+        resources.insert(C(0));
+        resources
+    }
+
+    fn names(plan: &Plan) -> Vec<Vec<&str>> {
+        plan.stages()
+            .iter()
+            .map(|stage| {
+                stage
+                    .iter()
+                    .map(|s| s.name().rsplit("::").next().unwrap())
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn conflicting_systems_run_in_insertion_order() {
+        let mut schedule = ParallelSchedule::new();
+        schedule.add_system(write_a);
+        schedule.add_system(write_b);
+        schedule.add_system(read_a_write_c);
+        schedule.add_system(read_a);
+
+        let plan = schedule.plan().unwrap();
+        assert_eq!(
+            names(&plan),
+            [vec!["write_a", "write_b"], vec!["read_a_write_c", "read_a"]]
+        );
+        assert!(plan.to_string().contains("read_a_write_c [reads "));
+        assert_eq!(plan.to_string().matches("stage ").count(), 2);
+
+        let resources = resources();
+        schedule.run(&resources).unwrap();
+        schedule.run(&resources).unwrap();
+        assert_eq!(resources.borrow::<C>().0, 3);
+    }
+
+    #[test]
+    fn labels_override_insertion_order() {
+        let mut schedule = ParallelSchedule::new();
+        schedule.add_system(read_a_write_c).after("a");
+        schedule.add_system(write_a).label("a");
+        schedule.add_system(write_b).before("a");
+
+        assert_eq!(
+            names(&schedule.plan().unwrap()),
+            [vec!["write_b"], vec!["write_a"], vec!["read_a_write_c"]]
+        );
+
+        let resources = resources();
+        schedule.run(&resources).unwrap();
+        assert_eq!(resources.borrow::<C>().0, 1);
+    }
+
+    #[test]
+    fn invalid_constraints_are_reported() {
+        let mut schedule = ParallelSchedule::new();
+        schedule.add_system(write_a).after("missing");
+        assert!(matches!(
+            schedule.plan(),
+            Err(ScheduleError::UnknownLabel {
+                label: "missing",
+                ..
+            })
+        ));
+
+        let mut schedule = ParallelSchedule::new();
+        schedule.add_system(write_a).label("a").after("b");
+        schedule.add_system(write_b).label("b").after("a");
+        assert!(matches!(schedule.plan(), Err(ScheduleError::Cycle(cycle)) if cycle.len() == 2));
+    }
+
+    #[test]
+    fn stage_systems_run_concurrently() {
+        if worker_count(2) < 2 {
+            return;
+        }
+        let barrier = Arc::new(Barrier::new(2));
+        let mut schedule = ParallelSchedule::new();
+        for _ in 0..2 {
+            let barrier = Arc::clone(&barrier);
+            schedule.add_system(move |_: &A| {
+                barrier.wait();
+            });
+        }
+        schedule.run(&resources()).unwrap();
+    }
+
+    #[test]
+    fn resources_that_are_not_send_can_be_present() {
+        let mut resources = resources();
+        resources.insert(std::rc::Rc::new(0u8));
+
+        let mut schedule = ParallelSchedule::new();
+        schedule.add_system(write_a);
+        schedule.add_system(write_b);
+        schedule.run(&resources).unwrap();
+        assert_eq!(resources.borrow::<B>().0, 1);
+    }
+
+    #[test]
+    fn failed_systems_stop_later_stages() {
+        let mut resources = resources();
+        resources.remove::<B>();
+
+        let mut schedule = ParallelSchedule::new();
+        schedule.add_system(write_a);
+        schedule.add_system(write_b);
+        schedule.add_system(read_a_write_c);
+        let error = schedule.run(&resources).unwrap_err();
+        assert!(matches!(
+            error,
+            ScheduleError::System(ref error) if error.error() == &BorrowError::NotFound(Type::of::<B>())
+        ));
+
+        // The other system in the failing stage still ran.
+        assert_eq!(resources.borrow::<A>().0, 1);
+        assert_eq!(resources.borrow::<C>().0, 0);
+    }
+}
@@ -0,0 +1,235 @@
+use ::rayon::iter::{
+    plumbing::{Consumer, ProducerCallback, UnindexedConsumer},
+    IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator,
+    IntoParallelRefMutIterator, ParallelExtend, ParallelIterator,
+};
+
+use crate::{SingletonMap, Type};
+
+/// Parallel iteration, available with the `rayon` feature.
+///
+/// The parallel iterators are indexed, so operations like `collect` and
+/// `reduce` still observe the insertion order of the map.
+impl<V> SingletonMap<V> {
+    /// Returns a parallel iterator over the key-value pairs of the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon::prelude::*;
+    /// use singletons::SingletonMap;
+    ///
+    /// let mut map = SingletonMap::new();
+    /// map.insert::<u8>(1);
+    /// map.insert::<u16>(2);
+    ///
+    /// let sum: i32 = map.par_iter().map(|(_, v)| *v).sum();
+    /// assert_eq!(sum, 3);
+    /// ```
+    pub fn par_iter(&self) -> ParIter<'_, V>
+    where
+        V: Sync,
+    {
+        ParIter(self.0.par_iter())
+    }
+
+    /// Returns a parallel iterator over the key-value pairs of the map, with
+    /// mutable references to the values.
+    pub fn par_iter_mut(&mut self) -> ParIterMut<'_, V>
+    where
+        V: Send,
+    {
+        ParIterMut(self.0.par_iter_mut())
+    }
+
+    /// Returns a parallel iterator over the values of the map.
+    pub fn par_values(&self) -> ParValues<'_, V>
+    where
+        V: Sync,
+    {
+        ParValues(self.0.par_values())
+    }
+
+    /// Returns a parallel iterator over mutable references to the values of
+    /// the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon::prelude::*;
+    /// use singletons::SingletonMap;
+    ///
+    /// let mut map = SingletonMap::new();
+    /// map.insert::<u8>(1);
+    /// map.insert::<u16>(2);
+    ///
+    /// map.par_values_mut().for_each(|v| *v *= 10);
+    /// assert_eq!(map.get::<u16>(), Some(&20));
+    /// ```
+    pub fn par_values_mut(&mut self) -> ParValuesMut<'_, V>
+    where
+        V: Send,
+    {
+        ParValuesMut(self.0.par_values_mut())
+    }
+}
+
+impl<V: Send> ParallelExtend<(Type, V)> for SingletonMap<V> {
+    /// Inserts each type key and value, as with
+    /// [`SingletonMap::insert_type()`].
+    ///
+    /// As with [`Extend`], entries are inserted in the order of the
+    /// iterator, and later values replace earlier ones for the same key.
+    fn par_extend<I>(&mut self, par_iter: I)
+    where
+        I: IntoParallelIterator<Item = (Type, V)>,
+    {
+        self.0.par_extend(par_iter);
+    }
+}
+
+impl<V: Send> IntoParallelIterator for SingletonMap<V> {
+    type Item = (Type, V);
+    type Iter = IntoParIter<V>;
+
+    fn into_par_iter(self) -> Self::Iter {
+        IntoParIter(self.0.into_par_iter())
+    }
+}
+
+impl<'a, V: Sync> IntoParallelIterator for &'a SingletonMap<V> {
+    type Item = (&'a Type, &'a V);
+    type Iter = ParIter<'a, V>;
+
+    fn into_par_iter(self) -> Self::Iter {
+        self.par_iter()
+    }
+}
+
+impl<'a, V: Send> IntoParallelIterator for &'a mut SingletonMap<V> {
+    type Item = (&'a Type, &'a mut V);
+    type Iter = ParIterMut<'a, V>;
+
+    fn into_par_iter(self) -> Self::Iter {
+        self.par_iter_mut()
+    }
+}
+
+/// A parallel iterator over the key-value pairs of a `SingletonMap`.
+///
+/// Created by [`SingletonMap::par_iter()`].
+#[derive(Clone)]
+pub struct ParIter<'a, V>(indexmap::map::rayon::ParIter<'a, Type, V>);
+
+/// A parallel iterator over the key-value pairs of a `SingletonMap`, with
+/// mutable references to the values.
+///
+/// Created by [`SingletonMap::par_iter_mut()`].
+pub struct ParIterMut<'a, V>(indexmap::map::rayon::ParIterMut<'a, Type, V>);
+
+/// A parallel iterator over the values of a `SingletonMap`.
+///
+/// Created by [`SingletonMap::par_values()`].
+#[derive(Clone)]
+pub struct ParValues<'a, V>(indexmap::map::rayon::ParValues<'a, Type, V>);
+
+/// A parallel iterator over mutable references to the values of a
+/// `SingletonMap`.
+///
+/// Created by [`SingletonMap::par_values_mut()`].
+pub struct ParValuesMut<'a, V>(indexmap::map::rayon::ParValuesMut<'a, Type, V>);
+
+/// An owning parallel iterator over the key-value pairs of a
+/// `SingletonMap`.
+pub struct IntoParIter<V>(indexmap::map::rayon::IntoParIter<Type, V>);
+
+/// Forwards the parallel iterator traits of a wrapper to the `indexmap`
+/// iterator it wraps.
+macro_rules! impl_parallel_iterator {
+    ($name:ident<$($lt:lifetime,)? V: $bound:ident> => $item:ty) => {
+        impl<$($lt,)? V: $bound> ParallelIterator for $name<$($lt,)? V> {
+            type Item = $item;
+
+            fn drive_unindexed<C>(self, consumer: C) -> C::Result
+            where
+                C: UnindexedConsumer<Self::Item>,
+            {
+                self.0.drive_unindexed(consumer)
+            }
+
+            fn opt_len(&self) -> Option<usize> {
+                self.0.opt_len()
+            }
+        }
+
+        impl<$($lt,)? V: $bound> IndexedParallelIterator for $name<$($lt,)? V> {
+            fn drive<C>(self, consumer: C) -> C::Result
+            where
+                C: Consumer<Self::Item>,
+            {
+                self.0.drive(consumer)
+            }
+
+            fn len(&self) -> usize {
+                self.0.len()
+            }
+
+            fn with_producer<CB>(self, callback: CB) -> CB::Output
+            where
+                CB: ProducerCallback<Self::Item>,
+            {
+                self.0.with_producer(callback)
+            }
+        }
+    };
+}
+
+impl_parallel_iterator!(ParIter<'a, V: Sync> => (&'a Type, &'a V));
+impl_parallel_iterator!(ParIterMut<'a, V: Send> => (&'a Type, &'a mut V));
+impl_parallel_iterator!(ParValues<'a, V: Sync> => &'a V);
+impl_parallel_iterator!(ParValuesMut<'a, V: Send> => &'a mut V);
+impl_parallel_iterator!(IntoParIter<V: Send> => (Type, V));
+
+#[cfg(test)]
+mod tests {
+    use ::rayon::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn test_singletonmap_par_iter_preserves_order() {
+        let mut map = SingletonMap::new();
+        map.insert::<u8>(1);
+        map.insert::<u16>(2);
+        map.insert::<u32>(3);
+
+        let keys: Vec<_> = map.par_iter().map(|(ty, _)| *ty).collect();
+        assert_eq!(
+            keys,
+            [Type::of::<u8>(), Type::of::<u16>(), Type::of::<u32>()]
+        );
+        assert_eq!(map.par_values().copied().collect::<Vec<_>>(), [1, 2, 3]);
+        assert_eq!(map.par_iter().len(), 3);
+
+        map.par_iter_mut().for_each(|(_, v)| *v += 1);
+        map.par_values_mut().for_each(|v| *v *= 10);
+        assert_eq!(map.values().copied().collect::<Vec<_>>(), [20, 30, 40]);
+
+        let owned: Vec<_> = map.into_par_iter().map(|(_, v)| v).collect();
+        assert_eq!(owned, [20, 30, 40]);
+    }
+
+    #[test]
+    fn test_singletonmap_par_extend() {
+        let mut map = SingletonMap::new();
+        map.insert::<u8>("old");
+        map.par_extend(vec![
+            (Type::of::<u16>(), "short"),
+            (Type::of::<u8>(), "byte"),
+        ]);
+
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get::<u8>(), Some(&"byte"));
+        assert_eq!(map.get_index(1), Some((&Type::of::<u16>(), &"short")));
+    }
+}
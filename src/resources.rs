@@ -0,0 +1,528 @@
+use std::{
+    cell::UnsafeCell,
+    fmt::{Debug, Display, Formatter},
+    ops::{Deref, DerefMut},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use indexmap::IndexMap;
+
+use crate::{erased::Erased, Type};
+
+/// The borrow flag of a slot that is mutably borrowed.
+const EXCLUSIVE: usize = usize::MAX;
+
+/// A set that stores one value per type, with a runtime borrow flag for
+/// each value.
+///
+/// This is the ECS-style counterpart of [`SingletonSet`]: values can be
+/// borrowed immutably with [`.borrow()`] and mutably with
+/// [`.borrow_mut()`] from a shared `&Resources`, so a caller can hold a
+/// [`Ref`] to one type and a [`RefMut`] to another at the same time.
+/// Borrowing the same type mutably more than once, or both mutably and
+/// immutably, is detected at runtime.
+///
+/// Values can be of any `'static` type, so `Resources` is neither `Send`
+/// nor `Sync`. The borrow flags are atomic all the same, so a
+/// [`ParallelSchedule`] can run systems that only use `Send + Sync` values
+/// on several threads at once.
+///
+/// # Example
+///
+/// ```
+/// use singletons::Resources;
+///
+/// struct Time(f64);
+/// struct Position(f64);
+///
+/// let mut resources = Resources::new();
+/// resources.insert(Time(0.5));
+/// resources.insert(Position(1.0));
+///
+/// let time = resources.borrow::<Time>();
+/// let mut position = resources.borrow_mut::<Position>();
+/// position.0 += time.0;
+///
+/// assert!(resources.try_borrow::<Position>().is_err());
+/// ```
+///
+/// [`SingletonSet`]: crate::SingletonSet
+/// [`ParallelSchedule`]: crate::ParallelSchedule
+/// [`.borrow()`]: Self::borrow()
+/// [`.borrow_mut()`]: Self::borrow_mut()
+#[derive(Debug, Default)]
+pub struct Resources(IndexMap<Type, ResourceCell>);
+
+/// A single value and its borrow flag.
+pub(crate) struct ResourceCell {
+    /// The number of shared borrows, or [`EXCLUSIVE`].
+    borrow: AtomicUsize,
+    value: UnsafeCell<Erased>,
+}
+
+impl ResourceCell {
+    fn new(value: Erased) -> Self {
+        ResourceCell {
+            borrow: AtomicUsize::new(0),
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    /// Registers a shared borrow, returning false if the value is mutably
+    /// borrowed.
+    pub(crate) fn acquire_shared(&self) -> bool {
+        let mut current = self.borrow.load(Ordering::Relaxed);
+        loop {
+            if current == EXCLUSIVE || current == EXCLUSIVE - 1 {
+                return false;
+            }
+            match self.borrow.compare_exchange_weak(
+                current,
+                current + 1,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return true,
+                Err(actual) => current = actual,
+            }
+        }
+    }
+
+    /// Registers a mutable borrow, returning false if the value is borrowed
+    /// at all.
+    pub(crate) fn acquire_exclusive(&self) -> bool {
+        self.borrow
+            .compare_exchange(0, EXCLUSIVE, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+    }
+
+    pub(crate) fn release_shared(&self) {
+        self.borrow.fetch_sub(1, Ordering::Release);
+    }
+
+    pub(crate) fn release_exclusive(&self) {
+        self.borrow.store(0, Ordering::Release);
+    }
+
+    pub(crate) fn is_mutably_borrowed(&self) -> bool {
+        self.borrow.load(Ordering::Relaxed) == EXCLUSIVE
+    }
+
+    /// Returns a reference to the value.
+    ///
+    /// # Safety
+    ///
+    /// A shared or exclusive borrow must be held for the returned lifetime.
+    pub(crate) unsafe fn get<T: 'static>(&self) -> &T {
+        (*self.value.get())
+            .downcast_ref()
+            .expect("resources are stored under their own type")
+    }
+
+    /// Returns a mutable reference to the value.
+    ///
+    /// # Safety
+    ///
+    /// An exclusive borrow must be held for the returned lifetime.
+    #[allow(clippy::mut_from_ref)]
+    pub(crate) unsafe fn get_mut<T: 'static>(&self) -> &mut T {
+        (*self.value.get())
+            .downcast_mut()
+            .expect("resources are stored under their own type")
+    }
+
+    fn into_inner<T: 'static>(self) -> T {
+        match self.value.into_inner().downcast() {
+            Ok(value) => value,
+            Err(_) => unreachable!("resources are stored under their own type"),
+        }
+    }
+}
+
+impl Debug for ResourceCell {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let borrow = match self.borrow.load(Ordering::Relaxed) {
+            0 => "unborrowed".to_string(),
+            EXCLUSIVE => "mutably borrowed".to_string(),
+            n => format!("borrowed {} times", n),
+        };
+        f.debug_struct("ResourceCell")
+            .field("borrow", &borrow)
+            .finish()
+    }
+}
+
+impl Resources {
+    /// Creates an empty `Resources` set.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use singletons::Resources;
+    /// let mut resources = Resources::new();
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        Resources(IndexMap::new())
+    }
+
+    /// Creates an empty `Resources` set with at least the specified
+    /// capacity.
+    #[inline]
+    #[must_use]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Resources(IndexMap::with_capacity(capacity))
+    }
+
+    /// Returns the number of values in the set.
+    #[inline]
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns true if the set contains no values.
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns true if the type is represented in the set.
+    #[must_use]
+    pub fn contains<T>(&self) -> bool
+    where
+        T: 'static,
+    {
+        self.0.contains_key(&Type::of::<T>())
+    }
+
+    /// Returns an iterator that visits each [`Type`] in the set in insertion
+    /// order.
+    pub fn types(&self) -> impl ExactSizeIterator<Item = &Type> + DoubleEndedIterator + '_ {
+        self.0.keys()
+    }
+
+    /// Inserts a value into the inferred type's slot, returning the previous
+    /// value, if any.
+    ///
+    /// This requires `&mut self`, so no value can be borrowed at the time.
+    pub fn insert<T>(&mut self, value: T) -> Option<T>
+    where
+        T: 'static,
+    {
+        self.0
+            .insert(Type::of::<T>(), ResourceCell::new(Erased::new(value)))
+            .map(ResourceCell::into_inner)
+    }
+
+    /// Removes the value of the specified type, returning it if it was
+    /// present.
+    pub fn remove<T>(&mut self) -> Option<T>
+    where
+        T: 'static,
+    {
+        self.0
+            .shift_remove(&Type::of::<T>())
+            .map(ResourceCell::into_inner)
+    }
+
+    /// Returns a mutable reference to the value of the specified type, if
+    /// it exists.
+    ///
+    /// This requires `&mut self`, so no borrow flag needs to be checked.
+    pub fn get_mut<T>(&mut self) -> Option<&mut T>
+    where
+        T: 'static,
+    {
+        self.0
+            .get_mut(&Type::of::<T>())
+            .and_then(|cell| cell.value.get_mut().downcast_mut())
+    }
+
+    /// Immutably borrows the value of the specified type.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the value is absent or currently mutably
+    /// borrowed. For a non-panicking variant, use [`.try_borrow()`].
+    ///
+    /// [`.try_borrow()`]: Self::try_borrow()
+    #[track_caller]
+    pub fn borrow<T>(&self) -> Ref<'_, T>
+    where
+        T: 'static,
+    {
+        match self.try_borrow() {
+            Ok(value) => value,
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    /// Mutably borrows the value of the specified type.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the value is absent or currently borrowed. For
+    /// a non-panicking variant, use [`.try_borrow_mut()`].
+    ///
+    /// [`.try_borrow_mut()`]: Self::try_borrow_mut()
+    #[track_caller]
+    pub fn borrow_mut<T>(&self) -> RefMut<'_, T>
+    where
+        T: 'static,
+    {
+        match self.try_borrow_mut() {
+            Ok(value) => value,
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    /// Immutably borrows the value of the specified type, returning an
+    /// error if it is absent or currently mutably borrowed.
+    pub fn try_borrow<T>(&self) -> Result<Ref<'_, T>, BorrowError>
+    where
+        T: 'static,
+    {
+        let ty = Type::of::<T>();
+        let cell = self.0.get(&ty).ok_or(BorrowError::NotFound(ty))?;
+        if !cell.acquire_shared() {
+            return Err(BorrowError::AlreadyMutablyBorrowed(ty));
+        }
+        Ok(Ref {
+            // SAFETY: the shared borrow is released when the `Ref` drops.
+            value: unsafe { cell.get() },
+            cell,
+        })
+    }
+
+    /// Mutably borrows the value of the specified type, returning an error
+    /// if it is absent or currently borrowed.
+    pub fn try_borrow_mut<T>(&self) -> Result<RefMut<'_, T>, BorrowError>
+    where
+        T: 'static,
+    {
+        let ty = Type::of::<T>();
+        let cell = self.0.get(&ty).ok_or(BorrowError::NotFound(ty))?;
+        if !cell.acquire_exclusive() {
+            return Err(if cell.is_mutably_borrowed() {
+                BorrowError::AlreadyMutablyBorrowed(ty)
+            } else {
+                BorrowError::AlreadyBorrowed(ty)
+            });
+        }
+        Ok(RefMut {
+            // SAFETY: the exclusive borrow is released when the `RefMut`
+            // drops.
+            value: unsafe { cell.get_mut() },
+            cell,
+        })
+    }
+
+    pub(crate) fn cell(&self, ty: &Type) -> Option<&ResourceCell> {
+        self.0.get(ty)
+    }
+
+    /// Wraps the values of a [`SingletonSet`], keeping their order.
+    ///
+    /// [`SingletonSet`]: crate::SingletonSet
+    pub(crate) fn from_values(values: IndexMap<Type, Erased>) -> Self {
+        Resources(
+            values
+                .into_iter()
+                .map(|(ty, value)| (ty, ResourceCell::new(value)))
+                .collect(),
+        )
+    }
+
+    /// Unwraps the values, keeping their order.
+    pub(crate) fn into_values(self) -> IndexMap<Type, Erased> {
+        self.0
+            .into_iter()
+            .map(|(ty, cell)| (ty, cell.value.into_inner()))
+            .collect()
+    }
+}
+
+/// An immutable borrow of a value in [`Resources`].
+///
+/// Created by [`Resources::borrow()`] and [`Resources::try_borrow()`].
+pub struct Ref<'a, T> {
+    value: &'a T,
+    cell: &'a ResourceCell,
+}
+
+impl<'a, T> Deref for Ref<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.value
+    }
+}
+
+impl<'a, T> Drop for Ref<'a, T> {
+    fn drop(&mut self) {
+        self.cell.release_shared();
+    }
+}
+
+impl<'a, T: Debug> Debug for Ref<'a, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+/// A mutable borrow of a value in [`Resources`].
+///
+/// Created by [`Resources::borrow_mut()`] and
+/// [`Resources::try_borrow_mut()`].
+pub struct RefMut<'a, T> {
+    value: &'a mut T,
+    cell: &'a ResourceCell,
+}
+
+impl<'a, T> Deref for RefMut<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.value
+    }
+}
+
+impl<'a, T> DerefMut for RefMut<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.value
+    }
+}
+
+impl<'a, T> Drop for RefMut<'a, T> {
+    fn drop(&mut self) {
+        self.cell.release_exclusive();
+    }
+}
+
+impl<'a, T: Debug> Debug for RefMut<'a, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+/// The error returned when a value in [`Resources`] cannot be borrowed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BorrowError {
+    /// There is no value of the type.
+    NotFound(Type),
+    /// A mutable borrow was requested while the value is immutably
+    /// borrowed.
+    AlreadyBorrowed(Type),
+    /// A borrow was requested while the value is mutably borrowed.
+    AlreadyMutablyBorrowed(Type),
+}
+
+impl BorrowError {
+    /// Returns the [`Type`] that could not be borrowed.
+    #[must_use]
+    pub fn ty(&self) -> &Type {
+        match self {
+            BorrowError::NotFound(ty)
+            | BorrowError::AlreadyBorrowed(ty)
+            | BorrowError::AlreadyMutablyBorrowed(ty) => ty,
+        }
+    }
+}
+
+impl Display for BorrowError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BorrowError::NotFound(ty) => write!(f, "resource `{}` does not exist", ty.as_name()),
+            BorrowError::AlreadyBorrowed(ty) => {
+                write!(f, "resource `{}` is already borrowed", ty.as_name())
+            }
+            BorrowError::AlreadyMutablyBorrowed(ty) => {
+                write!(f, "resource `{}` is already mutably borrowed", ty.as_name())
+            }
+        }
+    }
+}
+
+impl std::error::Error for BorrowError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resources_allow_disjoint_borrows() {
+        let mut resources = Resources::new();
+        resources.insert(1u8);
+        resources.insert(2u16);
+
+        let a = resources.borrow::<u8>();
+        let b = resources.borrow::<u8>();
+        let mut c = resources.borrow_mut::<u16>();
+        *c += u16::from(*a + *b);
+        drop((a, b, c));
+
+        assert_eq!(*resources.borrow::<u16>(), 4);
+        assert_eq!(resources.insert(5u16), Some(4));
+        assert_eq!(resources.remove::<u8>(), Some(1));
+    }
+
+    #[test]
+    fn resources_reject_conflicting_borrows() {
+        let mut resources = Resources::new();
+        resources.insert(1u8);
+
+        let shared = resources.borrow::<u8>();
+        assert_eq!(
+            resources.try_borrow_mut::<u8>().err(),
+            Some(BorrowError::AlreadyBorrowed(Type::of::<u8>()))
+        );
+        drop(shared);
+
+        let exclusive = resources.borrow_mut::<u8>();
+        assert_eq!(
+            resources.try_borrow::<u8>().err(),
+            Some(BorrowError::AlreadyMutablyBorrowed(Type::of::<u8>()))
+        );
+        assert_eq!(
+            resources.try_borrow_mut::<u8>().err(),
+            Some(BorrowError::AlreadyMutablyBorrowed(Type::of::<u8>()))
+        );
+        drop(exclusive);
+
+        assert_eq!(
+            resources.try_borrow::<u16>().err(),
+            Some(BorrowError::NotFound(Type::of::<u16>()))
+        );
+        assert!(resources.try_borrow_mut::<u8>().is_ok());
+    }
+
+    #[test]
+    #[should_panic(expected = "resource `u8` is already mutably borrowed")]
+    fn resources_panic_on_aliasing() {
+        let mut resources = Resources::new();
+        resources.insert(1u8);
+
+        let _exclusive = resources.borrow_mut::<u8>();
+        let _shared = resources.borrow::<u8>();
+    }
+
+    #[test]
+    fn resources_hold_values_that_are_not_thread_safe() {
+        use std::{cell::RefCell, rc::Rc};
+
+        let log = Rc::new(RefCell::new(Vec::<u8>::new()));
+        let mut resources = Resources::new();
+        resources.insert(Rc::clone(&log));
+
+        resources
+            .borrow::<Rc<RefCell<Vec<u8>>>>()
+            .borrow_mut()
+            .push(1);
+        assert_eq!(*log.borrow(), [1]);
+        assert!(resources.remove::<Rc<RefCell<Vec<u8>>>>().is_some());
+        assert_eq!(Rc::strong_count(&log), 1);
+    }
+}
@@ -0,0 +1,432 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt::{self, Display, Formatter},
+    marker::PhantomData,
+};
+
+use ::serde::{
+    de::{self, DeserializeSeed, MapAccess, Visitor},
+    ser::SerializeMap,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use indexmap::IndexMap;
+
+use crate::{SingletonMap, Type};
+
+/// Serializes the type by its full name, as reported by [`Type::as_str()`].
+///
+/// A `Type` cannot be deserialized on its own, since a name does not carry
+/// enough information to recover the type. Use
+/// [`TypeRegistry::type_seed()`] to look names up among a known set of
+/// types instead.
+impl Serialize for Type {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+/// Serializes the map as a map from the full name of each key type, as
+/// reported by [`Type::as_str()`], to its value, in insertion order.
+///
+/// Use [`TypeRegistry::serializable()`] to write registered aliases instead
+/// of full names, and [`TypeRegistry::map_seed()`] to read the map back.
+impl<V: Serialize> Serialize for SingletonMap<V> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.iter().map(|(ty, value)| (ty.as_str(), value)))
+    }
+}
+
+/// The set of types that can be read back by name, available with the
+/// `serde` feature.
+///
+/// Type names are only known at runtime from [`Type::as_str()`], so
+/// deserializing a [`Type`] or a [`SingletonMap`] needs a list of the types
+/// that are allowed to appear. Every registered type can be looked up by its
+/// full name, and optionally by a stable alias, which is unaffected by
+/// moving or renaming the type. When a type has an alias,
+/// [`.serializable()`] writes the alias instead of the full name.
+///
+/// Names that are not registered fail to deserialize, with an error that
+/// names the unknown type string.
+///
+/// # Example
+///
+/// ```
+/// use serde::de::DeserializeSeed;
+/// use singletons::{SingletonMap, TypeRegistry};
+///
+/// struct Volume;
+///
+/// let mut registry = TypeRegistry::new();
+/// registry.register::<u8>().register_as::<Volume>("volume");
+///
+/// let mut map = SingletonMap::new();
+/// map.insert::<u8>(1);
+/// map.insert::<Volume>(11);
+///
+/// let json = serde_json::to_string(&registry.serializable(&map)).unwrap();
+/// assert_eq!(json, r#"{"u8":1,"volume":11}"#);
+///
+/// let mut de = serde_json::Deserializer::from_str(&json);
+/// let copy: SingletonMap<i32> = registry.map_seed().deserialize(&mut de).unwrap();
+/// assert_eq!(copy, map);
+///
+/// let mut de = serde_json::Deserializer::from_str(r#"{"u16":2}"#);
+/// let error = registry.map_seed::<i32>().deserialize(&mut de).unwrap_err();
+/// assert!(error.to_string().starts_with("unknown type `u16`"));
+/// ```
+///
+/// [`.serializable()`]: Self::serializable()
+#[derive(Clone, Debug, Default)]
+pub struct TypeRegistry {
+    /// The registered types, in registration order, with their aliases.
+    types: IndexMap<Type, Option<String>>,
+    /// Every name that a registered type can be looked up by.
+    names: HashMap<String, Type>,
+}
+
+impl TypeRegistry {
+    /// Creates an empty `TypeRegistry`.
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        TypeRegistry {
+            types: IndexMap::new(),
+            names: HashMap::new(),
+        }
+    }
+
+    /// Returns the number of registered types.
+    #[inline]
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.types.len()
+    }
+
+    /// Returns true if no types are registered.
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.types.is_empty()
+    }
+
+    /// Registers the type under its full name.
+    ///
+    /// Registering a type more than once has no further effect.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a different type is already registered under the same
+    /// name.
+    pub fn register<T: 'static>(&mut self) -> &mut Self {
+        let ty = Type::of::<T>();
+        if !self.types.contains_key(&ty) {
+            self.add_name(ty.as_str().to_string(), ty);
+            self.types.insert(ty, None);
+        }
+        self
+    }
+
+    /// Registers the type under its full name and under `alias`, which is
+    /// used in place of the full name when serializing.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a different type is already registered under either name,
+    /// or if the type is already registered with a different alias.
+    pub fn register_as<T: 'static>(&mut self, alias: impl Into<String>) -> &mut Self {
+        let ty = Type::of::<T>();
+        let alias = alias.into();
+        self.register::<T>();
+        match &self.types[&ty] {
+            Some(existing) if *existing == alias => {}
+            Some(existing) => panic!("type `{}` is already registered as `{}`", ty, existing),
+            None => {
+                self.add_name(alias.clone(), ty);
+                self.types[&ty] = Some(alias);
+            }
+        }
+        self
+    }
+
+    /// Returns true if the type is registered.
+    #[must_use]
+    pub fn contains<T: 'static>(&self) -> bool {
+        self.types.contains_key(&Type::of::<T>())
+    }
+
+    /// Looks up a registered type by its full name or its alias.
+    pub fn resolve(&self, name: &str) -> Result<Type, UnknownType> {
+        self.names.get(name).copied().ok_or_else(|| UnknownType {
+            name: name.to_string(),
+        })
+    }
+
+    /// Returns the name that the type is serialized as: its alias, if it has
+    /// one, or its full name otherwise.
+    #[must_use]
+    pub fn name_of<'a>(&'a self, ty: &'a Type) -> &'a str {
+        match self.types.get(ty) {
+            Some(Some(alias)) => alias,
+            _ => ty.as_str(),
+        }
+    }
+
+    /// Returns an iterator that visits each registered [`Type`] in
+    /// registration order.
+    pub fn types(&self) -> impl ExactSizeIterator<Item = &Type> + DoubleEndedIterator + '_ {
+        self.types.keys()
+    }
+
+    /// Wraps the map so that it serializes its keys by their registered
+    /// aliases.
+    ///
+    /// Key types without an alias, including unregistered ones, are written
+    /// by their full name.
+    pub fn serializable<'a, V>(&'a self, map: &'a SingletonMap<V>) -> Serializable<'a, V> {
+        Serializable {
+            registry: self,
+            map,
+        }
+    }
+
+    /// Returns a [`DeserializeSeed`] that reads a [`Type`] from its name.
+    pub fn type_seed(&self) -> TypeSeed<'_> {
+        TypeSeed { registry: self }
+    }
+
+    /// Returns a [`DeserializeSeed`] that reads a [`SingletonMap`] written
+    /// by its [`Serialize`] implementation or by [`.serializable()`].
+    ///
+    /// Reading fails if a key is not a registered name, or if the same type
+    /// appears more than once.
+    ///
+    /// [`.serializable()`]: Self::serializable()
+    pub fn map_seed<V>(&self) -> MapSeed<'_, V> {
+        MapSeed {
+            registry: self,
+            marker: PhantomData,
+        }
+    }
+
+    fn add_name(&mut self, name: String, ty: Type) {
+        match self.names.get(&name) {
+            Some(existing) if *existing != ty => {
+                panic!(
+                    "the name `{}` is already registered for type `{}`",
+                    name, existing
+                )
+            }
+            _ => {
+                self.names.insert(name, ty);
+            }
+        }
+    }
+}
+
+/// The error returned when a type name is not registered in a
+/// [`TypeRegistry`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnknownType {
+    name: String,
+}
+
+impl UnknownType {
+    /// Returns the type name that was not found.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Display for UnknownType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown type `{}`", self.name)
+    }
+}
+
+impl Error for UnknownType {}
+
+/// A [`SingletonMap`] that serializes its keys by their registered aliases.
+///
+/// Created by [`TypeRegistry::serializable()`].
+#[derive(Debug)]
+pub struct Serializable<'a, V> {
+    registry: &'a TypeRegistry,
+    map: &'a SingletonMap<V>,
+}
+
+impl<'a, V> Clone for Serializable<'a, V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, V> Copy for Serializable<'a, V> {}
+
+impl<'a, V: Serialize> Serialize for Serializable<'a, V> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.map.len()))?;
+        for (ty, value) in self.map {
+            map.serialize_entry(self.registry.name_of(ty), value)?;
+        }
+        map.end()
+    }
+}
+
+/// Reads a [`Type`] from a name registered in a [`TypeRegistry`].
+///
+/// Created by [`TypeRegistry::type_seed()`].
+#[derive(Clone, Copy, Debug)]
+pub struct TypeSeed<'a> {
+    registry: &'a TypeRegistry,
+}
+
+impl<'a, 'de> DeserializeSeed<'de> for TypeSeed<'a> {
+    type Value = Type;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Type, D::Error> {
+        deserializer.deserialize_str(self)
+    }
+}
+
+impl<'a, 'de> Visitor<'de> for TypeSeed<'a> {
+    type Value = Type;
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("a registered type name")
+    }
+
+    fn visit_str<E: de::Error>(self, name: &str) -> Result<Type, E> {
+        self.registry.resolve(name).map_err(E::custom)
+    }
+}
+
+/// Reads a [`SingletonMap`] whose keys are names registered in a
+/// [`TypeRegistry`].
+///
+/// Created by [`TypeRegistry::map_seed()`].
+#[derive(Debug)]
+pub struct MapSeed<'a, V> {
+    registry: &'a TypeRegistry,
+    marker: PhantomData<fn() -> V>,
+}
+
+impl<'a, V> Clone for MapSeed<'a, V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, V> Copy for MapSeed<'a, V> {}
+
+impl<'a, 'de, V: Deserialize<'de>> DeserializeSeed<'de> for MapSeed<'a, V> {
+    type Value = SingletonMap<V>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'a, 'de, V: Deserialize<'de>> Visitor<'de> for MapSeed<'a, V> {
+    type Value = SingletonMap<V>;
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("a map from registered type names to values")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+        let mut map = SingletonMap::with_capacity(access.size_hint().unwrap_or(0));
+        while let Some(ty) = access.next_key_seed(self.registry.type_seed())? {
+            if map.contains_type(&ty) {
+                return Err(de::Error::custom(format_args!("duplicate type `{}`", ty)));
+            }
+            map.insert_type(ty, access.next_value()?);
+        }
+        Ok(map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod config {
+        pub struct Volume;
+        pub struct Brightness;
+    }
+
+    fn registry() -> TypeRegistry {
+        let mut registry = TypeRegistry::new();
+        registry
+            .register::<config::Volume>()
+            .register_as::<config::Brightness>("brightness");
+        registry
+    }
+
+    fn read(registry: &TypeRegistry, json: &str) -> Result<SingletonMap<u8>, serde_json::Error> {
+        let mut de = serde_json::Deserializer::from_str(json);
+        registry.map_seed().deserialize(&mut de)
+    }
+
+    #[test]
+    fn test_singletonmap_serde_round_trip() {
+        let registry = registry();
+        let mut map = SingletonMap::new();
+        map.insert::<config::Brightness>(80);
+        map.insert::<config::Volume>(11);
+
+        let volume = Type::of::<config::Volume>();
+        let plain = serde_json::to_string(&map).unwrap();
+        assert_eq!(
+            plain,
+            format!(
+                r#"{{"{}":80,"{}":11}}"#,
+                Type::of::<config::Brightness>(),
+                volume
+            )
+        );
+
+        let aliased = serde_json::to_string(&registry.serializable(&map)).unwrap();
+        assert_eq!(aliased, format!(r#"{{"brightness":80,"{}":11}}"#, volume));
+
+        assert_eq!(read(&registry, &plain).unwrap(), map);
+        let copy = read(&registry, &aliased).unwrap();
+        assert_eq!(
+            copy.keys().copied().collect::<Vec<_>>(),
+            map.keys().copied().collect::<Vec<_>>()
+        );
+
+        assert_eq!(
+            serde_json::to_string(&volume).unwrap(),
+            format!(r#""{}""#, volume)
+        );
+        let mut de = serde_json::Deserializer::from_str(r#""brightness""#);
+        assert_eq!(
+            registry.type_seed().deserialize(&mut de).unwrap(),
+            Type::of::<config::Brightness>()
+        );
+    }
+
+    #[test]
+    fn test_singletonmap_serde_rejects_unknown_and_duplicate_types() {
+        let registry = registry();
+
+        let error = read(&registry, r#"{"brightness":1,"Volume":2}"#).unwrap_err();
+        assert!(error.to_string().starts_with("unknown type `Volume`"));
+
+        let error = read(&registry, r#"{"brightness":1,"brightness":2}"#).unwrap_err();
+        assert!(error.to_string().starts_with("duplicate type `"));
+
+        assert_eq!(registry.resolve("u8").unwrap_err().name(), "u8");
+        assert_eq!(registry.len(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "already registered")]
+    fn test_type_registry_rejects_conflicting_aliases() {
+        let mut registry = registry();
+        registry.register_as::<u8>("brightness");
+    }
+}
@@ -1,17 +1,112 @@
-use std::any::Any;
+use std::{
+    fmt::{Debug, Formatter},
+    marker::PhantomData,
+    mem,
+};
 
 use indexmap::IndexMap;
 pub use indexmap::TryReserveError;
 
-use crate::Type;
+use crate::{
+    arena::Arena, erased::Erased, DeepSize, FrozenSingletonSet, MemoryReport, MemoryUsage,
+    Resources, SingletonSetBuilder, Type,
+};
 
 /// A hash map that uses the value's type as its key.
 ///
 /// This data structure can be used to create a locally-scoped Singleton out
 /// of any data type it holds. It ensures there is only one instance of any
 /// type, similar to a Singleton, without requiring a global scope.
-#[derive(Debug, Default)]
-pub struct SingletonSet(IndexMap<Type, Box<dyn Any>>);
+///
+/// Values no larger than a few machine words are stored inline in their
+/// slot, so only larger values cost a heap allocation each. A set created
+/// with [`SingletonSet::with_arena()`] places larger values in an arena
+/// owned by the set instead.
+///
+/// Because small values live inline, values do not have stable addresses: a
+/// value may move whenever the set is mutated, so a pointer to it is only
+/// valid for as long as the reference it came from.
+///
+/// Values are dropped in reverse insertion order.
+///
+/// The type parameter selects the [`Storage`] of the set. The default,
+/// [`Local`], accepts values of any type, so the set is neither `Send` nor
+/// `Sync`. A set created with [`SingletonSet::thread_safe()`] only accepts
+/// `Send + Sync` values, and is `Send + Sync` itself.
+#[derive(Debug)]
+pub struct SingletonSet<S: Storage = Local> {
+    map: IndexMap<Type, Erased>,
+    arena: Option<Arena>,
+    /// Incremented whenever values may have moved to a different index, to
+    /// invalidate outstanding [`Slot`]s.
+    generation: u64,
+    /// Hooks registered with [`SingletonSet::register_deep_size()`].
+    deep_sizes: IndexMap<Type, fn(&Erased) -> Option<usize>>,
+    marker: PhantomData<S>,
+}
+
+/// The kind of values a [`SingletonSet`] accepts, which decides whether the
+/// set can be shared between threads.
+///
+/// This trait is sealed and implemented for [`Local`] and [`ThreadSafe`].
+pub trait Storage: sealed::Sealed {}
+
+/// The default [`Storage`] of a [`SingletonSet`], which accepts values of
+/// any `'static` type.
+#[derive(Debug)]
+pub enum Local {}
+
+/// The [`Storage`] of a [`SingletonSet`] that only accepts `Send + Sync`
+/// values, which makes the set `Send + Sync` as well.
+///
+/// # Example
+///
+/// ```compile_fail
+/// use std::rc::Rc;
+/// use singletons::SingletonSet;
+///
+/// let mut set = SingletonSet::thread_safe();
+/// set.insert(Rc::new(1u8));
+/// ```
+#[derive(Debug)]
+pub enum ThreadSafe {}
+
+impl Storage for Local {}
+
+impl Storage for ThreadSafe {}
+
+/// A type whose values can be stored in a [`SingletonSet`] with the
+/// [`Storage`] `S`.
+///
+/// This trait is sealed. Every `'static` type is `Storable<Local>`, and
+/// every `'static + Send + Sync` type is `Storable<ThreadSafe>`.
+pub trait Storable<S: Storage>: sealed::Value<S> {}
+
+impl<S: Storage, T: sealed::Value<S>> Storable<S> for T {}
+
+mod sealed {
+    use super::{Local, ThreadSafe};
+
+    pub trait Sealed {}
+
+    impl Sealed for Local {}
+
+    impl Sealed for ThreadSafe {}
+
+    pub trait Value<S>: 'static {}
+
+    impl<T: 'static> Value<Local> for T {}
+
+    impl<T: 'static + Send + Sync> Value<ThreadSafe> for T {}
+}
+
+// SAFETY: a set with `ThreadSafe` storage only accepts `Send + Sync` values,
+// and the arena memory holding some of them is owned by the set alone.
+unsafe impl Send for SingletonSet<ThreadSafe> {}
+
+// SAFETY: as above. Shared access to the set only hands out shared
+// references to the values.
+unsafe impl Sync for SingletonSet<ThreadSafe> {}
 
 impl SingletonSet {
     /// Creates an empty `SingletonSet`.
@@ -28,7 +123,13 @@ impl SingletonSet {
     #[inline]
     #[must_use]
     pub fn new() -> Self {
-        SingletonSet(IndexMap::new())
+        SingletonSet {
+            map: IndexMap::new(),
+            arena: None,
+            generation: 0,
+            deep_sizes: IndexMap::new(),
+            marker: PhantomData,
+        }
     }
 
     /// Creates an empty `SingletonSet` with at least the specified capacity.
@@ -46,75 +147,200 @@ impl SingletonSet {
     #[inline]
     #[must_use]
     pub fn with_capacity(capacity: usize) -> Self {
-        SingletonSet(IndexMap::with_capacity(capacity))
+        SingletonSet {
+            map: IndexMap::with_capacity(capacity),
+            arena: None,
+            generation: 0,
+            deep_sizes: IndexMap::new(),
+            marker: PhantomData,
+        }
+    }
+
+    /// Creates a [`SingletonSetBuilder`], which reports duplicate types
+    /// instead of replacing values.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use singletons::SingletonSet;
+    /// let set = SingletonSet::builder().with(1u8).with(2u16).build().unwrap();
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn builder() -> SingletonSetBuilder {
+        SingletonSetBuilder::new()
+    }
+
+    /// Creates an empty `SingletonSet` that allocates its values from an
+    /// arena owned by the set.
+    ///
+    /// Instead of boxing each value that is too large to be stored inline,
+    /// the set bump-allocates it from the arena. The memory is released all
+    /// at once when the set is dropped, or made available for reuse by
+    /// [`.reset()`]. The arena does not allocate until the first value that
+    /// needs it is inserted.
+    ///
+    /// Memory used by a value that is replaced or cleared stays in use until
+    /// the set is reset, so this mode suits sets that are short-lived or
+    /// reset regularly.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use singletons::SingletonSet;
+    /// let mut set = SingletonSet::with_arena();
+    /// set.insert([0u64; 32]);
+    /// set.reset();
+    /// assert!(set.is_empty());
+    /// ```
+    ///
+    /// [`.reset()`]: Self::reset()
+    #[inline]
+    #[must_use]
+    pub fn with_arena() -> Self {
+        SingletonSet {
+            map: IndexMap::new(),
+            arena: Some(Arena::with_capacity(0)),
+            generation: 0,
+            deep_sizes: IndexMap::new(),
+            marker: PhantomData,
+        }
+    }
+
+    /// Creates an empty `SingletonSet` that allocates its values from an
+    /// arena with at least `bytes` bytes of initial capacity.
+    ///
+    /// See [`SingletonSet::with_arena()`] for details.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use singletons::SingletonSet;
+    /// let mut set = SingletonSet::with_arena_capacity(16 * 1024);
+    /// ```
+    #[must_use]
+    pub fn with_arena_capacity(bytes: usize) -> Self {
+        SingletonSet {
+            map: IndexMap::new(),
+            arena: Some(Arena::with_capacity(bytes)),
+            generation: 0,
+            deep_sizes: IndexMap::new(),
+            marker: PhantomData,
+        }
     }
+}
 
+impl SingletonSet<ThreadSafe> {
+    /// Creates an empty `SingletonSet` that only accepts `Send + Sync`
+    /// values, so it can be shared between threads.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use singletons::SingletonSet;
+    ///
+    /// let mut set = SingletonSet::thread_safe();
+    /// set.insert(42u32);
+    ///
+    /// std::thread::scope(|scope| {
+    ///     scope.spawn(|| assert_eq!(set.get::<u32>(), &42));
+    /// });
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn thread_safe() -> Self {
+        Self::default()
+    }
+}
+
+impl<S: Storage> SingletonSet<S> {
     /// Returns the number of elements the set can hold without reallocating.
     #[inline]
     #[must_use]
     pub fn capacity(&self) -> usize {
-        self.0.capacity()
+        self.map.capacity()
     }
 
     /// Returns the number of elements the set currently holds.
     #[inline]
     #[must_use]
     pub fn len(&self) -> usize {
-        self.0.len()
+        self.map.len()
     }
 
     /// Returns true if the set contains no elements.
     #[inline]
     #[must_use]
     pub fn is_empty(&self) -> bool {
-        self.0.is_empty()
+        self.map.is_empty()
     }
 
     /// Clears the set, removing all values.
-    #[inline]
+    ///
+    /// Values are dropped in reverse insertion order. The memory of an
+    /// arena-backed set is kept allocated but is not reused until
+    /// [`.reset()`] is called.
+    ///
+    /// [`.reset()`]: Self::reset()
     pub fn clear(&mut self) {
-        self.0.clear()
+        while self.map.pop().is_some() {}
+        self.generation += 1;
+    }
+
+    /// Clears the set and makes the memory of its arena available for reuse.
+    ///
+    /// This is intended for sets that are filled and emptied repeatedly,
+    /// such as one set per request. For sets without an arena, this is the
+    /// same as [`.clear()`].
+    ///
+    /// [`.clear()`]: Self::clear()
+    pub fn reset(&mut self) {
+        self.clear();
+        if let Some(arena) = &mut self.arena {
+            arena.reset();
+        }
     }
 
     /// Reserves capacity for at least `additional` more values.
     #[inline]
     pub fn reserve(&mut self, additional: usize) {
-        self.0.reserve(additional)
+        self.map.reserve(additional)
     }
 
     /// Tries to reserve capacity for at least `additional` more values.
     #[inline]
     pub fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
-        self.0.try_reserve(additional)
+        self.map.try_reserve(additional)
     }
 
     /// Shrinks the capacity of the set as much as possible.
     #[inline]
     pub fn shrink_to_fit(&mut self) {
-        self.0.shrink_to_fit()
+        self.map.shrink_to_fit()
     }
 
     /// Shrinks the capacity of the set as much as possible, but not less than
     /// `min_capacity`.
     #[inline]
     pub fn shrink_to(&mut self, min_capacity: usize) {
-        self.0.shrink_to(min_capacity)
+        self.map.shrink_to(min_capacity)
     }
 
     /// Inserts a value into the inferred type's slot.
     pub fn insert<T>(&mut self, value: T) -> Option<T>
     where
-        T: 'static,
+        T: Storable<S>,
     {
-        self.0
-            .insert(Type::of::<T>(), Box::new(value))
-            .and_then(|boxed| boxed.downcast().ok().map(|boxed| *boxed))
+        let erased = erase(&mut self.arena, value);
+        self.map
+            .insert(Type::of::<T>(), erased)
+            .and_then(|erased| erased.downcast().ok())
     }
 
     /// Inserts the default value of a type in the set.
     pub fn insert_default<T>(&mut self) -> Option<T>
     where
-        T: 'static + Default,
+        T: Storable<S> + Default,
     {
         self.insert(T::default())
     }
@@ -122,7 +348,7 @@ impl SingletonSet {
     /// Inserts a value into the inferred type's slot.
     pub fn insert_with<T>(&mut self, f: impl FnOnce() -> T) -> Option<T>
     where
-        T: 'static,
+        T: Storable<S>,
     {
         self.insert(f())
     }
@@ -133,7 +359,7 @@ impl SingletonSet {
     where
         T: 'static,
     {
-        self.0.contains_key(&Type::of::<T>())
+        self.map.contains_key(&Type::of::<T>())
     }
 
     /// Returns true if the type of the provided value is represented in the
@@ -144,12 +370,12 @@ impl SingletonSet {
     {
         // Parameter only used for type inference; we check type via Type::of::<T>()
         let _ = value;
-        self.0.contains_key(&Type::of::<T>())
+        self.map.contains_key(&Type::of::<T>())
     }
 
     /// Returns true if the supplied [`Type`] is represented in the set.
     pub fn contains_type(&self, t: &Type) -> bool {
-        self.0.contains_key(t)
+        self.map.contains_key(t)
     }
 
     /// Calls a closure with some value of the corresponding type's
@@ -189,7 +415,7 @@ impl SingletonSet {
     /// This method also returns the closure's return value.
     pub fn with_ref_or<T, R>(&mut self, default: T, f: impl FnOnce(&T) -> R) -> R
     where
-        T: 'static,
+        T: Storable<S>,
     {
         f(self.as_ref_or_insert::<T>(default))
     }
@@ -200,7 +426,7 @@ impl SingletonSet {
     /// This method also returns the closure's return value.
     pub fn with_ref_or_default<T, R>(&mut self, f: impl FnOnce(&T) -> R) -> R
     where
-        T: 'static + Default,
+        T: Storable<S> + Default,
     {
         f(self.as_ref_or_insert::<T>(T::default()))
     }
@@ -214,7 +440,7 @@ impl SingletonSet {
         f: impl FnOnce(&T) -> R,
     ) -> R
     where
-        T: 'static,
+        T: Storable<S>,
     {
         f(self.as_ref_or_insert_with(default))
     }
@@ -234,7 +460,7 @@ impl SingletonSet {
     /// slot, if it exists, returning its return value.
     pub fn with_mut<T, R>(&mut self, f: impl FnOnce(&mut T) -> R) -> R
     where
-        T: 'static + Default,
+        T: Storable<S> + Default,
     {
         f(self.as_mut())
     }
@@ -245,7 +471,7 @@ impl SingletonSet {
     /// This method returns the closure's return value.
     pub fn with_mut_or<T, R>(&mut self, default: T, f: impl FnOnce(&mut T) -> R) -> R
     where
-        T: 'static,
+        T: Storable<S>,
     {
         f(self.as_mut_or_insert::<T>(default))
     }
@@ -259,7 +485,7 @@ impl SingletonSet {
         f: impl FnOnce(&mut T) -> R,
     ) -> R
     where
-        T: 'static,
+        T: Storable<S>,
     {
         f(self.as_mut_or_insert_with(default))
     }
@@ -283,9 +509,9 @@ impl SingletonSet {
     where
         T: 'static,
     {
-        self.0
+        self.map
             .get(&Type::of::<T>())
-            .and_then(|boxed| boxed.downcast_ref::<T>())
+            .and_then(|erased| erased.downcast_ref::<T>())
     }
 
     /// This is an alias for [`Self::try_as_ref()`]
@@ -299,7 +525,7 @@ impl SingletonSet {
     /// This is an alias for [`Self::as_mut()`]
     pub fn get_mut<T>(&mut self) -> &mut T
     where
-        T: 'static + Default,
+        T: Storable<S> + Default,
     {
         self.as_mut()
     }
@@ -314,9 +540,9 @@ impl SingletonSet {
     where
         T: 'static,
     {
-        self.0
+        self.map
             .get_mut(&Type::of::<T>())
-            .and_then(|boxed| boxed.downcast_mut::<T>())
+            .and_then(|erased| erased.downcast_mut::<T>())
     }
 
     /// This is an alias for [`Self::try_as_mut()`]
@@ -335,11 +561,11 @@ impl SingletonSet {
     #[doc(alias = "get_or_insert()")]
     pub fn as_ref_or_insert<T>(&mut self, value: T) -> &T
     where
-        T: 'static,
+        T: Storable<S>,
     {
-        self.0
+        self.map
             .entry(Type::of::<T>())
-            .or_insert(Box::new(value))
+            .or_insert_with(|| erase(&mut self.arena, value))
             .downcast_ref::<T>()
             .expect("downcast must succeed: type T was just inserted with Type::of::<T>() as key")
     }
@@ -347,7 +573,7 @@ impl SingletonSet {
     /// This is an alias for [`Self::as_ref_or_insert()`]
     pub fn get_or_insert<T>(&mut self, value: T) -> &T
     where
-        T: 'static,
+        T: Storable<S>,
     {
         self.as_ref_or_insert(value)
     }
@@ -363,11 +589,11 @@ impl SingletonSet {
     #[doc(alias = "get_or_insert_mut()")]
     pub fn as_mut_or_insert<T>(&mut self, value: T) -> &mut T
     where
-        T: 'static,
+        T: Storable<S>,
     {
-        self.0
+        self.map
             .entry(Type::of::<T>())
-            .or_insert(Box::new(value))
+            .or_insert_with(|| erase(&mut self.arena, value))
             .downcast_mut::<T>()
             .expect("downcast must succeed: type T was just inserted with Type::of::<T>() as key")
     }
@@ -377,7 +603,7 @@ impl SingletonSet {
     /// [`.as_mut_or_insert(value)`]: Self::as_mut_or_insert()
     pub fn get_or_insert_mut<T>(&mut self, value: T) -> &mut T
     where
-        T: 'static,
+        T: Storable<S>,
     {
         self.as_mut_or_insert(value)
     }
@@ -388,11 +614,11 @@ impl SingletonSet {
     #[doc(alias = "get_or_insert_mut()")]
     pub fn as_ref_or_insert_with<T>(&mut self, default: impl FnOnce() -> T) -> &T
     where
-        T: 'static,
+        T: Storable<S>,
     {
-        self.0
+        self.map
             .entry(Type::of::<T>())
-            .or_insert_with(|| Box::new(default()))
+            .or_insert_with(|| erase(&mut self.arena, default()))
             .downcast_ref::<T>()
             .expect("downcast must succeed: type T was just inserted with Type::of::<T>() as key")
     }
@@ -400,7 +626,7 @@ impl SingletonSet {
     /// This is an alias for [`Self::as_ref_or_insert_with()`]
     pub fn get_or_insert_with<T>(&mut self, default: impl FnOnce() -> T) -> &T
     where
-        T: 'static,
+        T: Storable<S>,
     {
         self.as_ref_or_insert_with(default)
     }
@@ -411,11 +637,11 @@ impl SingletonSet {
     #[doc(alias = "get_or_insert_with_mut()")]
     pub fn as_mut_or_insert_with<T>(&mut self, default: impl FnOnce() -> T) -> &mut T
     where
-        T: 'static,
+        T: Storable<S>,
     {
-        self.0
+        self.map
             .entry(Type::of::<T>())
-            .or_insert_with(|| Box::new(default()))
+            .or_insert_with(|| erase(&mut self.arena, default()))
             .downcast_mut::<T>()
             .expect("downcast must succeed: type T was just inserted with Type::of::<T>() as key")
     }
@@ -425,7 +651,7 @@ impl SingletonSet {
     /// [`.as_mut_or_insert_with(default)`]: Self::as_mut_or_insert_with()
     pub fn get_or_insert_with_mut<T>(&mut self, default: impl FnOnce() -> T) -> &mut T
     where
-        T: 'static,
+        T: Storable<S>,
     {
         self.as_mut_or_insert_with(default)
     }
@@ -436,11 +662,216 @@ impl SingletonSet {
     /// store the elements.
     #[must_use]
     pub fn types(&self) -> Types<'_> {
-        Types(self.0.keys())
+        Types(self.map.keys())
+    }
+
+    /// Returns a handle to the slot of the specified type, if it exists.
+    ///
+    /// The handle can be resolved with [`.get_slot()`] or
+    /// [`.get_slot_mut()`] without hashing the type again, which makes it
+    /// suitable for repeated access to the same value. Handles should only
+    /// be resolved by the set that issued them.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use singletons::SingletonSet;
+    /// let mut set = SingletonSet::new();
+    /// set.insert(1u32);
+    ///
+    /// let slot = set.slot::<u32>().unwrap();
+    /// *set.get_slot_mut(&slot).unwrap() += 1;
+    /// assert_eq!(set.get_slot(&slot), Some(&2));
+    ///
+    /// set.clear();
+    /// assert_eq!(set.get_slot(&slot), None);
+    /// ```
+    ///
+    /// [`.get_slot()`]: Self::get_slot()
+    /// [`.get_slot_mut()`]: Self::get_slot_mut()
+    #[must_use]
+    pub fn slot<T>(&self) -> Option<Slot<T>>
+    where
+        T: 'static,
+    {
+        self.map.get_index_of(&Type::of::<T>()).map(|index| Slot {
+            index,
+            generation: self.generation,
+            marker: PhantomData,
+        })
+    }
+
+    /// Returns an immutable reference to the value behind a slot handle.
+    ///
+    /// This returns [`None`] if the value was removed or the set was
+    /// reordered since the handle was issued.
+    #[must_use]
+    pub fn get_slot<T>(&self, slot: &Slot<T>) -> Option<&T>
+    where
+        T: 'static,
+    {
+        if slot.generation != self.generation {
+            return None;
+        }
+        self.map
+            .get_index(slot.index)
+            .and_then(|(_, erased)| erased.downcast_ref::<T>())
+    }
+
+    /// Returns a mutable reference to the value behind a slot handle.
+    ///
+    /// This returns [`None`] if the value was removed or the set was
+    /// reordered since the handle was issued.
+    pub fn get_slot_mut<T>(&mut self, slot: &Slot<T>) -> Option<&mut T>
+    where
+        T: 'static,
+    {
+        if slot.generation != self.generation {
+            return None;
+        }
+        self.map
+            .get_index_mut(slot.index)
+            .and_then(|(_, erased)| erased.downcast_mut::<T>())
+    }
+
+    /// Converts the set into a read-only [`FrozenSingletonSet`] with faster
+    /// lookups.
+    ///
+    /// The values stay where they are, so freezing does not allocate except
+    /// for the lookup table. Use [`FrozenSingletonSet::thaw()`] to get the
+    /// set back, with its arena and [`.register_deep_size()`] hooks intact.
+    ///
+    /// The frozen set is `Send + Sync` if this set has [`ThreadSafe`]
+    /// storage.
+    ///
+    /// [`.register_deep_size()`]: Self::register_deep_size()
+    #[must_use]
+    pub fn freeze(self) -> FrozenSingletonSet<Self> {
+        FrozenSingletonSet::new(self)
+    }
+
+    /// Returns the type at `index` in insertion order.
+    pub(crate) fn type_at(&self, index: usize) -> Option<&Type> {
+        self.map.get_index(index).map(|(ty, _)| ty)
+    }
+
+    /// Returns the value at `index` in insertion order, if it has type `T`.
+    pub(crate) fn get_index<T: 'static>(&self, index: usize) -> Option<&T> {
+        self.map
+            .get_index(index)
+            .and_then(|(_, erased)| erased.downcast_ref::<T>())
+    }
+
+    /// Moves the values into [`Resources`] for the duration of `f`, so
+    /// they can be borrowed through runtime borrow flags, and moves them
+    /// back afterwards, even if `f` panics.
+    ///
+    /// The arena stays with the set, which outlives `f`, and the values keep
+    /// their indices, so slots remain valid.
+    pub(crate) fn lend<R>(&mut self, f: impl FnOnce(&Resources) -> R) -> R {
+        struct Lent<'a> {
+            map: &'a mut IndexMap<Type, Erased>,
+            resources: Resources,
+        }
+
+        impl<'a> Drop for Lent<'a> {
+            fn drop(&mut self) {
+                *self.map = mem::take(&mut self.resources).into_values();
+            }
+        }
+
+        let resources = Resources::from_values(mem::take(&mut self.map));
+        let lent = Lent {
+            map: &mut self.map,
+            resources,
+        };
+        f(&lent.resources)
+    }
+
+    /// Registers [`DeepSize`] as the way to measure the memory owned by
+    /// values of type `T` in [`.memory_report()`].
+    ///
+    /// The registration applies to the type, not to the current value, so
+    /// it remains in effect when the value is replaced or the set is
+    /// cleared.
+    ///
+    /// [`.memory_report()`]: Self::memory_report()
+    pub fn register_deep_size<T>(&mut self)
+    where
+        T: 'static + DeepSize,
+    {
+        self.deep_sizes.insert(Type::of::<T>(), |erased| {
+            erased.downcast_ref::<T>().map(T::deep_size)
+        });
+    }
+
+    /// Returns a report of the memory used by each type in the set, in
+    /// insertion order.
+    ///
+    /// The shallow size of each value is the size of its type. Deep sizes
+    /// are only reported for types registered with
+    /// [`.register_deep_size()`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use singletons::SingletonSet;
+    /// let mut set = SingletonSet::new();
+    /// set.insert(1u8);
+    /// set.insert(String::with_capacity(100));
+    /// set.register_deep_size::<String>();
+    ///
+    /// let mut report = set.memory_report();
+    /// report.sort_by_size();
+    /// let largest = report.iter().next().unwrap();
+    /// assert_eq!(largest.ty().as_name(), "String");
+    /// assert_eq!(largest.deep_size(), Some(100));
+    /// ```
+    ///
+    /// [`.register_deep_size()`]: Self::register_deep_size()
+    #[must_use]
+    pub fn memory_report(&self) -> MemoryReport {
+        MemoryReport::new(
+            self.map
+                .iter()
+                .map(|(ty, erased)| {
+                    let deep = self.deep_sizes.get(ty).and_then(|measure| measure(erased));
+                    MemoryUsage::new(*ty, ty.size_of(), deep)
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Erases the type of `value`, placing it in `arena` if the set has one.
+fn erase<T: 'static>(arena: &mut Option<Arena>, value: T) -> Erased {
+    match arena {
+        Some(arena) => Erased::new_in(value, arena),
+        None => Erased::new(value),
+    }
+}
+
+impl<S: Storage> Default for SingletonSet<S> {
+    fn default() -> Self {
+        SingletonSet {
+            map: IndexMap::new(),
+            arena: None,
+            generation: 0,
+            deep_sizes: IndexMap::new(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<S: Storage> Drop for SingletonSet<S> {
+    fn drop(&mut self) {
+        // Values must be dropped before the arena holding them, which the
+        // field order already guarantees, but in reverse insertion order.
+        self.clear();
     }
 }
 
-impl<T> AsRef<T> for SingletonSet
+impl<S: Storage, T> AsRef<T> for SingletonSet<S>
 where
     T: 'static,
 {
@@ -466,9 +897,9 @@ where
     }
 }
 
-impl<T> AsMut<T> for SingletonSet
+impl<S: Storage, T> AsMut<T> for SingletonSet<S>
 where
-    T: 'static + Default,
+    T: Storable<S> + Default,
 {
     /// Returns a mutable reference to the value of the specified type.
     ///
@@ -481,9 +912,36 @@ where
     }
 }
 
+/// A handle to the slot of type `T` in a [`SingletonSet`].
+///
+/// Created by [`SingletonSet::slot()`].
+pub struct Slot<T> {
+    index: usize,
+    generation: u64,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for Slot<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Slot<T> {}
+
+impl<T: 'static> Debug for Slot<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Slot")
+            .field("type", &Type::of::<T>())
+            .field("index", &self.index)
+            .field("generation", &self.generation)
+            .finish()
+    }
+}
+
 /// An iterator of the [`Type`]s in a [`SingletonSet`].
 #[derive(Clone)]
-pub struct Types<'a>(indexmap::map::Keys<'a, Type, Box<dyn Any>>);
+pub struct Types<'a>(indexmap::map::Keys<'a, Type, Erased>);
 
 impl<'a> Iterator for Types<'a> {
     type Item = &'a Type;
@@ -615,4 +1073,122 @@ mod tests {
         assert!(iter.next().is_some());
         assert_eq!(iter.next(), None);
     }
+
+    #[test]
+    fn singletonset_holds_small_and_large_values() {
+        let mut set = SingletonSet::new();
+
+        set.insert(7u8);
+        set.insert([3u64; 16]);
+        set.insert("small".to_string());
+
+        assert_eq!(set.insert(8u8), Some(7u8));
+        assert_eq!(set.insert([4u64; 16]), Some([3u64; 16]));
+        set.get_mut::<String>().push_str(" string");
+
+        assert_eq!(set.get::<u8>(), &8u8);
+        assert_eq!(set.get::<[u64; 16]>(), &[4u64; 16]);
+        assert_eq!(set.get::<String>(), "small string");
+    }
+
+    #[test]
+    fn singletonset_inline_values_allow_interior_mutability() {
+        use std::cell::Cell;
+
+        let mut set = SingletonSet::new();
+        set.insert(Cell::new(1u32));
+
+        set.try_as_ref::<Cell<u32>>().unwrap().set(2);
+        let cell = set.get::<Cell<u32>>();
+        cell.set(cell.get() * 10);
+        assert_eq!(set.get::<Cell<u32>>().get(), 20);
+    }
+
+    #[test]
+    fn singletonset_with_arena_drops_in_reverse_order() {
+        use std::{cell::RefCell, rc::Rc};
+
+        struct Noisy<const N: usize>(Rc<RefCell<Vec<usize>>>, [u64; 8]);
+
+        impl<const N: usize> Drop for Noisy<N> {
+            fn drop(&mut self) {
+                self.0.borrow_mut().push(N);
+            }
+        }
+
+        let dropped = Rc::new(RefCell::new(Vec::new()));
+        let mut set = SingletonSet::with_arena();
+
+        set.insert(Noisy::<1>(dropped.clone(), [0; 8]));
+        set.insert(Noisy::<2>(dropped.clone(), [0; 8]));
+        set.insert(Noisy::<3>(dropped.clone(), [0; 8]));
+        set.insert(4u8);
+
+        set.reset();
+        assert!(set.is_empty());
+        assert_eq!(*dropped.borrow(), vec![3, 2, 1]);
+
+        set.insert(Noisy::<1>(dropped.clone(), [0; 8]));
+        set.insert(Noisy::<2>(dropped.clone(), [0; 8]));
+        assert!(set.insert(Noisy::<1>(dropped.clone(), [1; 8])).is_some());
+        assert_eq!(*dropped.borrow(), vec![3, 2, 1, 1]);
+        assert_eq!(set.get::<Noisy<1>>().1, [1; 8]);
+
+        drop(set);
+        assert_eq!(*dropped.borrow(), vec![3, 2, 1, 1, 2, 1]);
+    }
+
+    #[test]
+    fn singletonset_freeze_and_thaw_keep_storage() {
+        let mut set = SingletonSet::with_arena();
+        set.insert(1u8);
+        set.insert(String::with_capacity(10));
+        set.register_deep_size::<String>();
+
+        let set = set.freeze().thaw();
+        assert!(set.arena.is_some());
+        assert_eq!(
+            set.memory_report().iter().nth(1).unwrap().deep_size(),
+            Some(10)
+        );
+
+        let mut set = SingletonSet::new();
+        set.insert(1u8);
+        let set = set.freeze().thaw();
+        assert!(set.map[&Type::of::<u8>()].is_inline());
+    }
+
+    #[test]
+    fn singletonset_slots_are_invalidated_by_clear() {
+        let mut set = SingletonSet::new();
+        set.insert(1u8);
+        set.insert(2u16);
+
+        let slot = set.slot::<u16>().unwrap();
+        assert!(set.slot::<u32>().is_none());
+        assert_eq!(set.get_slot(&slot), Some(&2));
+
+        set.insert(3u16);
+        set.insert(4u32);
+        assert_eq!(set.get_slot(&slot), Some(&3));
+
+        set.clear();
+        set.insert(1u8);
+        set.insert(5u16);
+        assert_eq!(set.get_slot(&slot), None);
+        assert_eq!(set.get_slot_mut(&slot), None);
+
+        let slot = set.slot::<u16>().unwrap();
+        assert_eq!(set.get_slot(&slot), Some(&5));
+    }
+
+    #[test]
+    fn set_macro_inserts_every_value() {
+        let set = crate::singleton_set![1u8, 2u16, "three",];
+        assert_eq!(set.len(), 3);
+        assert_eq!(set.get::<&str>(), &"three");
+
+        let empty: SingletonSet = crate::singleton_set![];
+        assert!(empty.is_empty());
+    }
 }
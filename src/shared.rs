@@ -0,0 +1,437 @@
+use std::{
+    any::Any,
+    future::Future,
+    marker::PhantomData,
+    pin::Pin,
+    sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError},
+    task::{Context, Poll, Waker},
+    time::{Duration, Instant},
+};
+
+use indexmap::IndexMap;
+
+use crate::{FrozenSingletonSet, Type};
+
+/// A thread-safe set that stores one shared value per type.
+///
+/// Unlike [`SingletonSet`], this set is `Send + Sync` and is used through a
+/// shared reference, so it can be placed in an [`Arc`] and used by many
+/// threads or tasks at once. Values are stored behind an [`Arc`] and handed
+/// out as clones of it.
+///
+/// Components that depend on a value published by another component can
+/// wait for it with [`.wait_for()`] in async code, or
+/// [`.wait_for_blocking()`] elsewhere. The future returned by
+/// [`.wait_for()`] only uses [`Waker`]s from the standard library, so it
+/// works with any executor.
+///
+/// # Example
+///
+/// ```
+/// use std::{sync::Arc, thread, time::Duration};
+/// use singletons::SharedSingletonSet;
+///
+/// struct Database(&'static str);
+///
+/// let set = Arc::new(SharedSingletonSet::new());
+///
+/// let publisher = set.clone();
+/// thread::spawn(move || publisher.insert(Database("postgres://")));
+///
+/// let db = set.wait_for_blocking::<Database>(Duration::from_secs(10)).unwrap();
+/// assert_eq!(db.0, "postgres://");
+/// ```
+///
+/// [`SingletonSet`]: crate::SingletonSet
+/// [`.wait_for()`]: Self::wait_for()
+/// [`.wait_for_blocking()`]: Self::wait_for_blocking()
+#[derive(Debug, Default)]
+pub struct SharedSingletonSet {
+    inner: Mutex<Inner>,
+    inserted: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    values: IndexMap<Type, Arc<dyn Any + Send + Sync>>,
+    /// The wakers of pending [`WaitFor`] futures, each tagged with the id of
+    /// the future that registered it.
+    wakers: IndexMap<Type, Vec<(u64, Waker)>>,
+    /// The id for the next [`WaitFor`] future to register a waker.
+    next_waiter: u64,
+}
+
+impl SharedSingletonSet {
+    /// Creates an empty `SharedSingletonSet`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use singletons::SharedSingletonSet;
+    /// let set = SharedSingletonSet::new();
+    /// ```
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of values the set currently holds.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.lock().values.len()
+    }
+
+    /// Returns true if the set contains no values.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.lock().values.is_empty()
+    }
+
+    /// Returns true if the type is represented in the set.
+    #[must_use]
+    pub fn contains<T>(&self) -> bool
+    where
+        T: 'static,
+    {
+        self.lock().values.contains_key(&Type::of::<T>())
+    }
+
+    /// Returns a snapshot of the [`Type`]s in the set, in insertion order.
+    #[must_use]
+    pub fn types(&self) -> Vec<Type> {
+        self.lock().values.keys().copied().collect()
+    }
+
+    /// Inserts a value into the inferred type's slot, returning the
+    /// previous value, if any.
+    ///
+    /// Every task and thread waiting for the type is woken up.
+    pub fn insert<T>(&self, value: T) -> Option<Arc<T>>
+    where
+        T: 'static + Send + Sync,
+    {
+        self.insert_arc(Arc::new(value))
+    }
+
+    /// Inserts a shared value into the inferred type's slot, returning the
+    /// previous value, if any.
+    ///
+    /// Every task and thread waiting for the type is woken up.
+    pub fn insert_arc<T>(&self, value: Arc<T>) -> Option<Arc<T>>
+    where
+        T: 'static + Send + Sync,
+    {
+        let ty = Type::of::<T>();
+        let (previous, wakers) = {
+            let mut inner = self.lock();
+            let previous = inner.values.insert(ty, value);
+            (previous, inner.wakers.swap_remove(&ty))
+        };
+
+        self.inserted.notify_all();
+        for (_, waker) in wakers.into_iter().flatten() {
+            waker.wake();
+        }
+
+        previous.and_then(|previous| previous.downcast().ok())
+    }
+
+    /// Returns the value of the specified type, if it exists.
+    #[must_use]
+    pub fn get<T>(&self) -> Option<Arc<T>>
+    where
+        T: 'static + Send + Sync,
+    {
+        get(&self.lock(), &Type::of::<T>())
+    }
+
+    /// Removes the value of the specified type from the set, returning it if
+    /// it was present.
+    pub fn remove<T>(&self) -> Option<Arc<T>>
+    where
+        T: 'static + Send + Sync,
+    {
+        self.lock()
+            .values
+            .shift_remove(&Type::of::<T>())
+            .and_then(|value| value.downcast().ok())
+    }
+
+    /// Returns a future that resolves to the value of the specified type
+    /// once it has been inserted.
+    ///
+    /// If the value is already in the set, the future resolves immediately.
+    pub fn wait_for<T>(&self) -> WaitFor<'_, T>
+    where
+        T: 'static + Send + Sync,
+    {
+        WaitFor {
+            set: self,
+            ty: Type::of::<T>(),
+            waiter: None,
+            marker: PhantomData,
+        }
+    }
+
+    /// Blocks the current thread until a value of the specified type is
+    /// inserted, or until `timeout` has elapsed.
+    ///
+    /// Returns [`None`] if the timeout elapsed before a value was inserted.
+    /// A timeout too large to be represented as a deadline, such as
+    /// [`Duration::MAX`], waits without a time limit.
+    pub fn wait_for_blocking<T>(&self, timeout: Duration) -> Option<Arc<T>>
+    where
+        T: 'static + Send + Sync,
+    {
+        let ty = Type::of::<T>();
+        let deadline = Instant::now().checked_add(timeout);
+        let mut inner = self.lock();
+        loop {
+            if let Some(value) = get(&inner, &ty) {
+                return Some(value);
+            }
+            inner = match deadline {
+                Some(deadline) => {
+                    let remaining = deadline.checked_duration_since(Instant::now())?;
+                    self.inserted
+                        .wait_timeout(inner, remaining)
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .0
+                }
+                None => self
+                    .inserted
+                    .wait(inner)
+                    .unwrap_or_else(PoisonError::into_inner),
+            };
+        }
+    }
+
+    /// Converts the set into a read-only [`FrozenSingletonSet`] with faster
+    /// lookups, which is also `Send + Sync`.
+    ///
+    /// The insertion order is preserved. Use [`FrozenSingletonSet::thaw()`]
+    /// to convert it back.
+    #[must_use]
+    pub fn freeze(self) -> FrozenSingletonSet<Self> {
+        FrozenSingletonSet::new(self)
+    }
+
+    pub(crate) fn into_values(self) -> IndexMap<Type, Arc<dyn Any + Send + Sync>> {
+        self.inner
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner)
+            .values
+    }
+
+    pub(crate) fn from_values(values: IndexMap<Type, Arc<dyn Any + Send + Sync>>) -> Self {
+        let inner = Inner {
+            values,
+            wakers: IndexMap::new(),
+            next_waiter: 0,
+        };
+        SharedSingletonSet {
+            inner: Mutex::new(inner),
+            inserted: Condvar::new(),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        // Every operation leaves `Inner` consistent, so a panic in another
+        // thread is no reason to fail here.
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+fn get<T>(inner: &Inner, ty: &Type) -> Option<Arc<T>>
+where
+    T: 'static + Send + Sync,
+{
+    inner
+        .values
+        .get(ty)
+        .and_then(|value| value.clone().downcast().ok())
+}
+
+/// A future that resolves once a value of type `T` is inserted into a
+/// [`SharedSingletonSet`].
+///
+/// Created by [`SharedSingletonSet::wait_for()`]. Dropping a pending future
+/// unregisters its waker.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct WaitFor<'a, T> {
+    set: &'a SharedSingletonSet,
+    ty: Type,
+    /// The id of the waker this future registered, if any.
+    waiter: Option<u64>,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<'a, T> Future for WaitFor<'a, T>
+where
+    T: 'static + Send + Sync,
+{
+    type Output = Arc<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let ty = this.ty;
+        let mut inner = this.set.lock();
+        if let Some(value) = get(&inner, &ty) {
+            return Poll::Ready(value);
+        }
+
+        // The waker is still registered unless an insert has taken it since
+        // the last poll.
+        let id = this.waiter;
+        let registered = inner
+            .wakers
+            .get_mut(&ty)
+            .and_then(|wakers| wakers.iter_mut().find(|(waiter, _)| Some(*waiter) == id));
+        match registered {
+            Some((_, waker)) => {
+                if !waker.will_wake(cx.waker()) {
+                    *waker = cx.waker().clone();
+                }
+            }
+            None => {
+                let id = inner.next_waiter;
+                inner.next_waiter += 1;
+                inner
+                    .wakers
+                    .entry(ty)
+                    .or_default()
+                    .push((id, cx.waker().clone()));
+                this.waiter = Some(id);
+            }
+        }
+        Poll::Pending
+    }
+}
+
+impl<'a, T> Drop for WaitFor<'a, T> {
+    fn drop(&mut self) {
+        let id = match self.waiter {
+            Some(id) => id,
+            None => return,
+        };
+        let mut inner = self.set.lock();
+        if let Some(wakers) = inner.wakers.get_mut(&self.ty) {
+            wakers.retain(|(waiter, _)| *waiter != id);
+            if wakers.is_empty() {
+                inner.wakers.swap_remove(&self.ty);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        task::Wake,
+        thread,
+    };
+
+    use super::*;
+
+    struct CountingWaker(AtomicUsize);
+
+    impl Wake for CountingWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn shared_singletonset_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<SharedSingletonSet>();
+    }
+
+    #[test]
+    fn shared_singletonset_replaces_values() {
+        let set = SharedSingletonSet::new();
+
+        assert_eq!(set.insert(1u32), None);
+        assert_eq!(set.insert(2u32).as_deref(), Some(&1));
+        assert_eq!(set.get::<u32>().as_deref(), Some(&2));
+        assert_eq!(set.types(), [Type::of::<u32>()]);
+        assert_eq!(set.remove::<u32>().as_deref(), Some(&2));
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    fn wait_for_wakes_on_insert() {
+        let set = SharedSingletonSet::new();
+        let counter = Arc::new(CountingWaker(AtomicUsize::new(0)));
+        let waker = Waker::from(counter.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        let mut future = set.wait_for::<u32>();
+        assert!(Pin::new(&mut future).poll(&mut cx).is_pending());
+        assert!(Pin::new(&mut future).poll(&mut cx).is_pending());
+
+        set.insert(1u8);
+        assert_eq!(counter.0.load(Ordering::SeqCst), 0);
+
+        set.insert(7u32);
+        assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+        match Pin::new(&mut future).poll(&mut cx) {
+            Poll::Ready(value) => assert_eq!(*value, 7),
+            Poll::Pending => panic!("the value was inserted"),
+        }
+    }
+
+    #[test]
+    fn dropping_wait_for_unregisters_its_waker() {
+        let set = SharedSingletonSet::new();
+        let counter = Arc::new(CountingWaker(AtomicUsize::new(0)));
+        let waker = Waker::from(counter.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        let mut first = set.wait_for::<u32>();
+        let mut second = set.wait_for::<u32>();
+        assert!(Pin::new(&mut first).poll(&mut cx).is_pending());
+        assert!(Pin::new(&mut second).poll(&mut cx).is_pending());
+        assert_eq!(set.lock().wakers[&Type::of::<u32>()].len(), 2);
+
+        drop(first);
+        assert_eq!(set.lock().wakers[&Type::of::<u32>()].len(), 1);
+        drop(second);
+        assert!(set.lock().wakers.is_empty());
+
+        set.insert(7u32);
+        assert_eq!(counter.0.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn wait_for_blocking_times_out_or_receives() {
+        let set = Arc::new(SharedSingletonSet::new());
+
+        assert_eq!(
+            set.wait_for_blocking::<u32>(Duration::from_millis(10)),
+            None
+        );
+
+        let publisher = set.clone();
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            publisher.insert(5u32);
+        });
+
+        let value = set.wait_for_blocking::<u32>(Duration::from_secs(10));
+        assert_eq!(value.as_deref(), Some(&5));
+        handle.join().unwrap();
+
+        let publisher = set.clone();
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            publisher.insert(6u64);
+        });
+
+        let value = set.wait_for_blocking::<u64>(Duration::MAX);
+        assert_eq!(value.as_deref(), Some(&6));
+        handle.join().unwrap();
+    }
+}
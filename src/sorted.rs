@@ -0,0 +1,282 @@
+use std::{collections::BTreeMap, ops::Bound};
+
+use crate::Type;
+
+/// A map that uses types as keys, like [`SingletonMap`], but iterates in
+/// sorted order.
+///
+/// [`SingletonMap`] iterates in insertion order, which depends on the order
+/// in which the map was filled. A `SortedSingletonMap` is backed by a
+/// `BTreeMap` and iterates in the order of [`Type`]'s [`Ord`]
+/// implementation: by full type name, falling back to the [`TypeId`] for
+/// distinct types that share a name. This makes the output of iteration
+/// reproducible across runs, which is useful for snapshots and dumps.
+///
+/// Because entries are sorted by name, all types under a module path can be
+/// found with [`.range_by_prefix()`].
+///
+/// # Example
+///
+/// ```
+/// use singletons::SortedSingletonMap;
+///
+/// let mut map = SortedSingletonMap::new();
+/// map.insert::<u8>("byte");
+/// map.insert::<bool>("flag");
+/// map.insert::<String>("text");
+///
+/// let names: Vec<_> = map.keys().map(|ty| ty.as_str()).collect();
+/// assert_eq!(names, ["alloc::string::String", "bool", "u8"]);
+///
+/// let strings: Vec<_> = map.range_by_prefix("alloc::").map(|(_, v)| *v).collect();
+/// assert_eq!(strings, ["text"]);
+/// ```
+///
+/// [`SingletonMap`]: crate::SingletonMap
+/// [`TypeId`]: std::any::TypeId
+/// [`.range_by_prefix()`]: Self::range_by_prefix()
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SortedSingletonMap<V> {
+    map: BTreeMap<Type, V>,
+    /// The keys of `map` by name, so they can be found by a name prefix.
+    /// Each list is sorted like the keys themselves.
+    names: BTreeMap<&'static str, Vec<Type>>,
+}
+
+impl<V> SortedSingletonMap<V> {
+    /// Creates an empty `SortedSingletonMap`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use singletons::SortedSingletonMap;
+    /// let mut map: SortedSingletonMap<String> = SortedSingletonMap::new();
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        SortedSingletonMap {
+            map: BTreeMap::new(),
+            names: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the number of elements in the map.
+    #[inline]
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns true if the map contains no elements.
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Removes all elements from the map.
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.names.clear();
+    }
+
+    /// Inserts a value for the specified key type, returning the previous
+    /// value, if any.
+    pub fn insert<K: 'static>(&mut self, value: V) -> Option<V> {
+        self.insert_type(Type::of::<K>(), value)
+    }
+
+    fn insert_type(&mut self, ty: Type, value: V) -> Option<V> {
+        let previous = self.map.insert(ty, value);
+        if previous.is_none() {
+            let types = self.names.entry(ty.static_str()).or_default();
+            let index = types.partition_point(|other| *other < ty);
+            types.insert(index, ty);
+        }
+        previous
+    }
+
+    /// Returns true if the map contains a value for the specified key type.
+    #[must_use]
+    pub fn contains_key<K: 'static>(&self) -> bool {
+        self.map.contains_key(&Type::of::<K>())
+    }
+
+    /// Returns a reference to the value for the specified key type, if it
+    /// exists.
+    #[must_use]
+    pub fn get<K: 'static>(&self) -> Option<&V> {
+        self.map.get(&Type::of::<K>())
+    }
+
+    /// Returns a mutable reference to the value for the specified key type,
+    /// if it exists.
+    pub fn get_mut<K: 'static>(&mut self) -> Option<&mut V> {
+        self.map.get_mut(&Type::of::<K>())
+    }
+
+    /// Removes the value for the specified key type, returning it if it
+    /// existed.
+    pub fn remove<K: 'static>(&mut self) -> Option<V> {
+        let ty = Type::of::<K>();
+        let value = self.map.remove(&ty)?;
+        if let Some(types) = self.names.get_mut(ty.as_str()) {
+            types.retain(|other| *other != ty);
+            if types.is_empty() {
+                self.names.remove(ty.as_str());
+            }
+        }
+        Some(value)
+    }
+
+    /// Returns the entry with the smallest key.
+    #[must_use]
+    pub fn first(&self) -> Option<(&Type, &V)> {
+        self.map.iter().next()
+    }
+
+    /// Returns the entry with the largest key.
+    #[must_use]
+    pub fn last(&self) -> Option<(&Type, &V)> {
+        self.map.iter().next_back()
+    }
+
+    /// Returns an iterator over the keys, in sorted order.
+    pub fn keys(&self) -> std::collections::btree_map::Keys<'_, Type, V> {
+        self.map.keys()
+    }
+
+    /// Returns an iterator over the values, in the sorted order of their
+    /// keys.
+    pub fn values(&self) -> std::collections::btree_map::Values<'_, Type, V> {
+        self.map.values()
+    }
+
+    /// Returns an iterator over mutable references to the values, in the
+    /// sorted order of their keys.
+    pub fn values_mut(&mut self) -> std::collections::btree_map::ValuesMut<'_, Type, V> {
+        self.map.values_mut()
+    }
+
+    /// Returns an iterator over the entries, in sorted order.
+    pub fn iter(&self) -> std::collections::btree_map::Iter<'_, Type, V> {
+        self.map.iter()
+    }
+
+    /// Returns an iterator over the entries with mutable references to the
+    /// values, in sorted order.
+    pub fn iter_mut(&mut self) -> std::collections::btree_map::IterMut<'_, Type, V> {
+        self.map.iter_mut()
+    }
+
+    /// Returns an iterator over the entries whose full type name starts
+    /// with `prefix`, in sorted order.
+    ///
+    /// The names are those reported by [`Type::as_str()`], so a module
+    /// path such as `"my_crate::events::"` selects the types declared in
+    /// that module and its submodules.
+    pub fn range_by_prefix<'a>(
+        &'a self,
+        prefix: &'a str,
+    ) -> impl Iterator<Item = (&'a Type, &'a V)> + 'a {
+        self.names
+            .range::<str, _>((Bound::Included(prefix), Bound::Unbounded))
+            .take_while(move |(name, _)| name.starts_with(prefix))
+            .flat_map(|(_, types)| types)
+            .filter_map(move |ty| self.map.get_key_value(ty))
+    }
+}
+
+impl<V> Default for SortedSingletonMap<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V> FromIterator<(Type, V)> for SortedSingletonMap<V> {
+    fn from_iter<I: IntoIterator<Item = (Type, V)>>(iter: I) -> Self {
+        let mut map = SortedSingletonMap::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<V> Extend<(Type, V)> for SortedSingletonMap<V> {
+    fn extend<I: IntoIterator<Item = (Type, V)>>(&mut self, iter: I) {
+        for (ty, value) in iter {
+            self.insert_type(ty, value);
+        }
+    }
+}
+
+impl<V> IntoIterator for SortedSingletonMap<V> {
+    type Item = (Type, V);
+    type IntoIter = std::collections::btree_map::IntoIter<Type, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.map.into_iter()
+    }
+}
+
+impl<'a, V> IntoIterator for &'a SortedSingletonMap<V> {
+    type Item = (&'a Type, &'a V);
+    type IntoIter = std::collections::btree_map::Iter<'a, Type, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.map.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod events {
+        pub struct Joined;
+        pub struct Left;
+        pub mod nested {
+            pub struct Reloaded;
+        }
+    }
+    mod eventsx {
+        pub struct Other;
+    }
+
+    #[test]
+    fn sorted_map_iterates_by_name() {
+        let map: SortedSingletonMap<u8> = [
+            (Type::of::<u16>(), 1),
+            (Type::of::<u8>(), 2),
+            (Type::of::<bool>(), 3),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(map.values().copied().collect::<Vec<_>>(), [3, 1, 2]);
+        assert_eq!(map.first(), Some((&Type::of::<bool>(), &3)));
+        assert_eq!(map.last(), Some((&Type::of::<u8>(), &2)));
+    }
+
+    #[test]
+    fn sorted_map_ranges_by_prefix() {
+        let mut map = SortedSingletonMap::new();
+        map.insert::<eventsx::Other>("other");
+        map.insert::<events::nested::Reloaded>("reloaded");
+        map.insert::<events::Left>("left");
+        map.insert::<u8>("byte");
+        map.insert::<events::Joined>("joined");
+
+        let prefix = format!("{}::events::", module_path!());
+        let values: Vec<_> = map.range_by_prefix(&prefix).map(|(_, v)| *v).collect();
+        assert_eq!(values, ["joined", "left", "reloaded"]);
+
+        assert_eq!(map.range_by_prefix("u").count(), 1);
+        assert_eq!(map.range_by_prefix("zzz").count(), 0);
+        assert_eq!(map.range_by_prefix("").count(), 5);
+
+        assert_eq!(map.remove::<events::Left>(), Some("left"));
+        let values: Vec<_> = map.range_by_prefix(&prefix).map(|(_, v)| *v).collect();
+        assert_eq!(values, ["joined", "reloaded"]);
+    }
+}
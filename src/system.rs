@@ -0,0 +1,744 @@
+use std::{
+    fmt::{Debug, Display, Formatter},
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+};
+
+use crate::{resources::ResourceCell, BorrowError, Resources, SingletonSet, Storage, Type};
+
+/// The types a [`System`] reads and writes.
+///
+/// Access is declared up front by each [`SystemParam`], so the borrows a
+/// system needs can be acquired before it runs and released afterwards.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Access {
+    reads: Vec<Type>,
+    writes: Vec<Type>,
+}
+
+impl Access {
+    /// Creates an empty access declaration.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declares immutable access to a type.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the type is already declared as written.
+    pub fn add_read(&mut self, ty: Type) {
+        assert!(
+            !self.writes.contains(&ty),
+            "`{}` is accessed both mutably and immutably",
+            ty.as_name()
+        );
+        if !self.reads.contains(&ty) {
+            self.reads.push(ty);
+        }
+    }
+
+    /// Declares mutable access to a type.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the type is already declared as read or
+    /// written.
+    pub fn add_write(&mut self, ty: Type) {
+        assert!(
+            !self.reads.contains(&ty),
+            "`{}` is accessed both mutably and immutably",
+            ty.as_name()
+        );
+        assert!(
+            !self.writes.contains(&ty),
+            "`{}` is accessed mutably more than once",
+            ty.as_name()
+        );
+        self.writes.push(ty);
+    }
+
+    /// Returns the types that are read, in declaration order.
+    #[must_use]
+    pub fn reads(&self) -> &[Type] {
+        &self.reads
+    }
+
+    /// Returns the types that are written, in declaration order.
+    #[must_use]
+    pub fn writes(&self) -> &[Type] {
+        &self.writes
+    }
+
+    /// Returns true if systems with these two declarations cannot run at
+    /// the same time, because one of them writes a type the other accesses.
+    #[must_use]
+    pub fn conflicts_with(&self, other: &Access) -> bool {
+        self.writes
+            .iter()
+            .any(|ty| other.reads.contains(ty) || other.writes.contains(ty))
+            || other.writes.iter().any(|ty| self.reads.contains(ty))
+    }
+}
+
+/// A parameter of a function that can be run as a [`System`].
+///
+/// This is implemented for `&T`, `&mut T`, [`Res<T>`], [`ResMut<T>`], and
+/// [`Option`] of any of those, which is [`None`] if the value is absent
+/// instead of causing an error.
+pub trait SystemParam {
+    /// Fetches the parameter from [`Resources`] for any lifetime.
+    type Fetch: for<'w> ParamFetch<'w>;
+
+    /// Declares the types this parameter reads and writes.
+    fn access(access: &mut Access);
+}
+
+/// Fetches a [`SystemParam`] that borrows from [`Resources`] for `'w`.
+pub trait ParamFetch<'w> {
+    /// The type passed to the function.
+    type Item;
+
+    /// Fetches the parameter from `resources`.
+    ///
+    /// # Safety
+    ///
+    /// The borrows declared by [`SystemParam::access()`] must be held for
+    /// `'w`.
+    unsafe fn fetch(resources: &'w Resources) -> Result<Self::Item, BorrowError>;
+}
+
+/// The type a [`SystemParam`] is passed to the function as, for `'w`.
+type ParamItem<'w, P> = <<P as SystemParam>::Fetch as ParamFetch<'w>>::Item;
+
+fn cell<T: 'static>(resources: &Resources) -> Result<&ResourceCell, BorrowError> {
+    let ty = Type::of::<T>();
+    resources.cell(&ty).ok_or(BorrowError::NotFound(ty))
+}
+
+/// The [`ParamFetch`] of `&T`.
+pub struct FetchRef<T>(PhantomData<fn() -> T>);
+
+impl<T: 'static> SystemParam for &T {
+    type Fetch = FetchRef<T>;
+
+    fn access(access: &mut Access) {
+        access.add_read(Type::of::<T>());
+    }
+}
+
+impl<'w, T: 'static> ParamFetch<'w> for FetchRef<T> {
+    type Item = &'w T;
+
+    unsafe fn fetch(resources: &'w Resources) -> Result<Self::Item, BorrowError> {
+        Ok(cell::<T>(resources)?.get())
+    }
+}
+
+/// The [`ParamFetch`] of `&mut T`.
+pub struct FetchMut<T>(PhantomData<fn() -> T>);
+
+impl<T: 'static> SystemParam for &mut T {
+    type Fetch = FetchMut<T>;
+
+    fn access(access: &mut Access) {
+        access.add_write(Type::of::<T>());
+    }
+}
+
+impl<'w, T: 'static> ParamFetch<'w> for FetchMut<T> {
+    type Item = &'w mut T;
+
+    unsafe fn fetch(resources: &'w Resources) -> Result<Self::Item, BorrowError> {
+        Ok(cell::<T>(resources)?.get_mut())
+    }
+}
+
+/// The [`ParamFetch`] of [`Res<T>`].
+pub struct FetchRes<T>(PhantomData<fn() -> T>);
+
+impl<'a, T: 'static> SystemParam for Res<'a, T> {
+    type Fetch = FetchRes<T>;
+
+    fn access(access: &mut Access) {
+        access.add_read(Type::of::<T>());
+    }
+}
+
+impl<'w, T: 'static> ParamFetch<'w> for FetchRes<T> {
+    type Item = Res<'w, T>;
+
+    unsafe fn fetch(resources: &'w Resources) -> Result<Self::Item, BorrowError> {
+        Ok(Res {
+            value: cell::<T>(resources)?.get(),
+        })
+    }
+}
+
+/// The [`ParamFetch`] of [`ResMut<T>`].
+pub struct FetchResMut<T>(PhantomData<fn() -> T>);
+
+impl<'a, T: 'static> SystemParam for ResMut<'a, T> {
+    type Fetch = FetchResMut<T>;
+
+    fn access(access: &mut Access) {
+        access.add_write(Type::of::<T>());
+    }
+}
+
+impl<'w, T: 'static> ParamFetch<'w> for FetchResMut<T> {
+    type Item = ResMut<'w, T>;
+
+    unsafe fn fetch(resources: &'w Resources) -> Result<Self::Item, BorrowError> {
+        Ok(ResMut {
+            value: cell::<T>(resources)?.get_mut(),
+        })
+    }
+}
+
+/// The [`ParamFetch`] of [`Option<P>`], where `F` is the fetch of `P`.
+pub struct FetchOption<F>(PhantomData<fn() -> F>);
+
+impl<P: SystemParam> SystemParam for Option<P> {
+    type Fetch = FetchOption<P::Fetch>;
+
+    fn access(access: &mut Access) {
+        P::access(access);
+    }
+}
+
+impl<'w, F: ParamFetch<'w>> ParamFetch<'w> for FetchOption<F> {
+    type Item = Option<F::Item>;
+
+    unsafe fn fetch(resources: &'w Resources) -> Result<Self::Item, BorrowError> {
+        match F::fetch(resources) {
+            Ok(item) => Ok(Some(item)),
+            Err(BorrowError::NotFound(_)) => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+}
+
+/// The parameters of a system that can run on the worker threads of a
+/// [`ParallelSchedule`], because every value they access is `Send + Sync`.
+///
+/// This is implemented for the parameters that implement [`SystemParam`]
+/// for a `Send + Sync` type, and for tuples of such parameters.
+///
+/// # Safety
+///
+/// Every type declared by [`SystemParam::access()`] must be `Send + Sync`,
+/// since [`Resources`] itself is not.
+///
+/// # Example
+///
+/// A system that uses a value that is not `Send + Sync` can run in a
+/// [`Schedule`], but not in a [`ParallelSchedule`]:
+///
+/// ```compile_fail
+/// use std::rc::Rc;
+/// use singletons::ParallelSchedule;
+///
+/// fn count(counter: &Rc<u32>) {}
+///
+/// ParallelSchedule::new().add_system(count);
+/// ```
+///
+/// [`ParallelSchedule`]: crate::ParallelSchedule
+pub unsafe trait ParallelParam {}
+
+// SAFETY: each of these declares access to `T` alone, which is
+// `Send + Sync`.
+unsafe impl<T: 'static + Send + Sync> ParallelParam for &T {}
+
+unsafe impl<T: 'static + Send + Sync> ParallelParam for &mut T {}
+
+unsafe impl<'a, T: 'static + Send + Sync> ParallelParam for Res<'a, T> {}
+
+unsafe impl<'a, T: 'static + Send + Sync> ParallelParam for ResMut<'a, T> {}
+
+// SAFETY: `Option<P>` declares the same access as `P`.
+unsafe impl<P: ParallelParam> ParallelParam for Option<P> {}
+
+/// Immutable access to a value in [`Resources`], as a [`SystemParam`].
+pub struct Res<'w, T> {
+    value: &'w T,
+}
+
+impl<'w, T> Deref for Res<'w, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.value
+    }
+}
+
+impl<'w, T: Debug> Debug for Res<'w, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+/// Mutable access to a value in [`Resources`], as a [`SystemParam`].
+pub struct ResMut<'w, T> {
+    value: &'w mut T,
+}
+
+impl<'w, T> Deref for ResMut<'w, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.value
+    }
+}
+
+impl<'w, T> DerefMut for ResMut<'w, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.value
+    }
+}
+
+impl<'w, T: Debug> Debug for ResMut<'w, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+/// A unit of work that runs against [`Resources`].
+///
+/// Systems use [`Resources`] rather than [`SingletonSet`] because a single
+/// call can borrow several values, some of them mutably, which needs the
+/// per-value borrow flags that [`Resources`] keeps. A [`Schedule`] can still
+/// run against a [`SingletonSet`] with [`Schedule::run_on()`].
+///
+/// Functions whose parameters all implement [`SystemParam`] become systems
+/// through [`IntoSystem`].
+pub trait System {
+    /// Returns the name of the system, for diagnostics.
+    fn name(&self) -> &str;
+
+    /// Returns the types the system reads and writes.
+    fn access(&self) -> &Access;
+
+    /// Runs the system, borrowing its parameters from `resources`.
+    ///
+    /// This fails without running the system if a required value is absent
+    /// or one of the declared types is already borrowed.
+    fn run(&mut self, resources: &Resources) -> Result<(), BorrowError>;
+}
+
+/// Conversion into a [`System`].
+///
+/// This is implemented for every function of up to eight parameters that
+/// implement [`SystemParam`]. `Params` is the tuple of parameter types,
+/// and is always inferred.
+pub trait IntoSystem<Params> {
+    /// The system this converts into.
+    type System: System + 'static;
+
+    /// Converts `self` into a system.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the parameters access the same type both
+    /// mutably and immutably, or mutably more than once.
+    fn into_system(self) -> Self::System;
+}
+
+/// A [`System`] that calls a function.
+pub struct FunctionSystem<F, Params> {
+    func: F,
+    name: &'static str,
+    access: Access,
+    marker: PhantomData<fn() -> Params>,
+}
+
+impl<F, Params> Debug for FunctionSystem<F, Params> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FunctionSystem")
+            .field("name", &self.name)
+            .field("access", &self.access)
+            .finish()
+    }
+}
+
+/// The borrows held while a system runs, released when dropped.
+struct Borrows<'w> {
+    resources: &'w Resources,
+    access: &'w Access,
+    /// The number of leading reads and writes that were acquired.
+    reads: usize,
+    writes: usize,
+}
+
+impl<'w> Borrows<'w> {
+    fn acquire(resources: &'w Resources, access: &'w Access) -> Result<Self, BorrowError> {
+        let mut borrows = Borrows {
+            resources,
+            access,
+            reads: 0,
+            writes: 0,
+        };
+        for ty in &access.reads {
+            if let Some(cell) = resources.cell(ty) {
+                if !cell.acquire_shared() {
+                    return Err(BorrowError::AlreadyMutablyBorrowed(*ty));
+                }
+            }
+            borrows.reads += 1;
+        }
+        for ty in &access.writes {
+            if let Some(cell) = resources.cell(ty) {
+                if !cell.acquire_exclusive() {
+                    return Err(if cell.is_mutably_borrowed() {
+                        BorrowError::AlreadyMutablyBorrowed(*ty)
+                    } else {
+                        BorrowError::AlreadyBorrowed(*ty)
+                    });
+                }
+            }
+            borrows.writes += 1;
+        }
+        Ok(borrows)
+    }
+}
+
+impl<'w> Drop for Borrows<'w> {
+    fn drop(&mut self) {
+        for ty in &self.access.reads[..self.reads] {
+            if let Some(cell) = self.resources.cell(ty) {
+                cell.release_shared();
+            }
+        }
+        for ty in &self.access.writes[..self.writes] {
+            if let Some(cell) = self.resources.cell(ty) {
+                cell.release_exclusive();
+            }
+        }
+    }
+}
+
+macro_rules! impl_system {
+    ($($param:ident),*) => {
+        impl<F, $($param),*> IntoSystem<($($param,)*)> for F
+        where
+            F: 'static,
+            $($param: SystemParam + 'static,)*
+            for<'a> &'a mut F: FnMut($($param),*) + FnMut($(ParamItem<'_, $param>),*),
+        {
+            type System = FunctionSystem<F, ($($param,)*)>;
+
+            #[allow(unused_mut)]
+            fn into_system(self) -> Self::System {
+                let mut access = Access::new();
+                $($param::access(&mut access);)*
+                FunctionSystem {
+                    func: self,
+                    name: std::any::type_name::<F>(),
+                    access,
+                    marker: PhantomData,
+                }
+            }
+        }
+
+        impl<F, $($param),*> System for FunctionSystem<F, ($($param,)*)>
+        where
+            $($param: SystemParam,)*
+            for<'a> &'a mut F: FnMut($($param),*) + FnMut($(ParamItem<'_, $param>),*),
+        {
+            fn name(&self) -> &str {
+                self.name
+            }
+
+            fn access(&self) -> &Access {
+                &self.access
+            }
+
+            #[allow(non_snake_case, clippy::too_many_arguments)]
+            fn run(&mut self, resources: &Resources) -> Result<(), BorrowError> {
+                // Calling through a generic function lets the compiler pick
+                // the `FnMut` impl over the parameter items.
+                fn call<$($param),*>(mut func: impl FnMut($($param),*), $($param: $param),*) {
+                    func($($param),*);
+                }
+
+                let _borrows = Borrows::acquire(resources, &self.access)?;
+                // SAFETY: `_borrows` holds every declared borrow until after
+                // the call, and the items cannot outlive the call.
+                $(let $param = unsafe { <$param::Fetch as ParamFetch>::fetch(resources)? };)*
+                call(&mut self.func, $($param),*);
+                Ok(())
+            }
+        }
+
+        // SAFETY: a tuple of parameters accesses what its elements do.
+        unsafe impl<$($param: ParallelParam),*> ParallelParam for ($($param,)*) {}
+    };
+}
+
+impl_system!();
+impl_system!(P0);
+impl_system!(P0, P1);
+impl_system!(P0, P1, P2);
+impl_system!(P0, P1, P2, P3);
+impl_system!(P0, P1, P2, P3, P4);
+impl_system!(P0, P1, P2, P3, P4, P5);
+impl_system!(P0, P1, P2, P3, P4, P5, P6);
+impl_system!(P0, P1, P2, P3, P4, P5, P6, P7);
+
+/// A list of systems that run in order.
+///
+/// # Example
+///
+/// ```
+/// use singletons::{Res, Resources, Schedule};
+///
+/// struct Time(u32);
+/// struct Position(u32);
+///
+/// fn advance(time: &mut Time) {
+///     time.0 += 1;
+/// }
+///
+/// fn follow(time: Res<Time>, position: &mut Position) {
+///     position.0 += time.0;
+/// }
+///
+/// let mut resources = Resources::new();
+/// resources.insert(Time(0));
+/// resources.insert(Position(0));
+///
+/// let mut schedule = Schedule::new();
+/// schedule.add_system(advance).add_system(follow);
+/// schedule.run(&resources).unwrap();
+/// schedule.run(&resources).unwrap();
+///
+/// assert_eq!(resources.borrow::<Position>().0, 3);
+/// ```
+#[derive(Default)]
+pub struct Schedule {
+    systems: Vec<Box<dyn System>>,
+}
+
+impl Schedule {
+    /// Creates an empty schedule.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of systems in the schedule.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.systems.len()
+    }
+
+    /// Returns true if the schedule contains no systems.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.systems.is_empty()
+    }
+
+    /// Appends a system to the schedule.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the system's parameters conflict with each
+    /// other, as described in [`IntoSystem::into_system()`].
+    pub fn add_system<Params>(&mut self, system: impl IntoSystem<Params>) -> &mut Self {
+        self.systems.push(Box::new(system.into_system()));
+        self
+    }
+
+    /// Appends a system to the schedule, for use in a builder chain.
+    ///
+    /// Conflicts are handled as in [`.add_system()`].
+    ///
+    /// [`.add_system()`]: Self::add_system()
+    #[must_use]
+    pub fn with_system<Params>(mut self, system: impl IntoSystem<Params>) -> Self {
+        self.add_system(system);
+        self
+    }
+
+    /// Runs each system in the order it was added.
+    ///
+    /// This stops at the first system that cannot borrow its parameters.
+    pub fn run(&mut self, resources: &Resources) -> Result<(), SystemError> {
+        for system in &mut self.systems {
+            system
+                .run(resources)
+                .map_err(|error| SystemError::new(system.name().to_string(), error))?;
+        }
+        Ok(())
+    }
+
+    /// Runs each system in the order it was added, borrowing its
+    /// parameters from the values in `set`.
+    ///
+    /// The values are moved into [`Resources`] for the duration of the run
+    /// and back into `set` afterwards, so this behaves like [`.run()`]
+    /// without copying them by hand.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use singletons::{Schedule, SingletonSet};
+    ///
+    /// struct Time(u32);
+    /// struct State(u32);
+    ///
+    /// fn tick(time: &Time, state: &mut State) {
+    ///     state.0 += time.0;
+    /// }
+    ///
+    /// let mut set = SingletonSet::new();
+    /// set.insert(Time(2));
+    /// set.insert(State(0));
+    ///
+    /// let mut schedule = Schedule::new().with_system(tick);
+    /// schedule.run_on(&mut set).unwrap();
+    ///
+    /// assert_eq!(set.get::<State>().0, 2);
+    /// ```
+    ///
+    /// [`.run()`]: Self::run()
+    pub fn run_on<S: Storage>(&mut self, set: &mut SingletonSet<S>) -> Result<(), SystemError> {
+        set.lend(|resources| self.run(resources))
+    }
+}
+
+impl Debug for Schedule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.systems.iter().map(|system| system.name()))
+            .finish()
+    }
+}
+
+/// The error returned when a system in a [`Schedule`] cannot run.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SystemError {
+    system: String,
+    error: BorrowError,
+}
+
+impl SystemError {
+    pub(crate) fn new(system: String, error: BorrowError) -> Self {
+        SystemError { system, error }
+    }
+
+    /// Returns the name of the system that could not run.
+    #[must_use]
+    pub fn system(&self) -> &str {
+        &self.system
+    }
+
+    /// Returns the reason the system could not run.
+    #[must_use]
+    pub fn error(&self) -> &BorrowError {
+        &self.error
+    }
+}
+
+impl Display for SystemError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "system `{}` could not run: {}", self.system, self.error)
+    }
+}
+
+impl std::error::Error for SystemError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Counter(u32);
+    struct Step(u32);
+
+    fn step(counter: &mut Counter, step: &Step) {
+        counter.0 += step.0;
+    }
+
+    #[test]
+    fn schedule_runs_systems_in_order() {
+        let mut resources = Resources::new();
+        resources.insert(Counter(0));
+        resources.insert(Step(2));
+
+        fn check(counter: Res<Counter>) {
+            assert_eq!(counter.0 % 2, 0);
+        }
+        fn scale(mut counter: ResMut<Counter>, factor: Option<Res<u8>>) {
+            assert!(factor.is_none());
+            counter.0 *= 10;
+        }
+
+        let mut schedule = Schedule::new()
+            .with_system(step)
+            .with_system(check)
+            .with_system(scale);
+        schedule.run(&resources).unwrap();
+        schedule.run(&resources).unwrap();
+
+        assert_eq!(resources.borrow::<Counter>().0, 220);
+        assert_eq!(schedule.len(), 3);
+    }
+
+    #[test]
+    fn schedule_reports_missing_and_borrowed_values() {
+        let mut resources = Resources::new();
+        resources.insert(Counter(0));
+
+        let mut schedule = Schedule::new().with_system(step);
+        let error = schedule.run(&resources).unwrap_err();
+        assert_eq!(error.error(), &BorrowError::NotFound(Type::of::<Step>()));
+        assert!(error.system().ends_with("step"));
+
+        resources.insert(Step(1));
+        let counter = resources.borrow::<Counter>();
+        let error = schedule.run(&resources).unwrap_err();
+        assert_eq!(
+            error.error(),
+            &BorrowError::AlreadyBorrowed(Type::of::<Counter>())
+        );
+        drop(counter);
+
+        // A failed run releases the borrows it acquired.
+        schedule.run(&resources).unwrap();
+        assert_eq!(resources.borrow_mut::<Step>().0, 1);
+    }
+
+    #[test]
+    fn schedule_runs_on_a_singleton_set() {
+        let mut set = SingletonSet::with_arena();
+        set.insert(Counter(1));
+        set.insert([0u64; 8]);
+        let slot = set.slot::<Counter>().unwrap();
+
+        let mut schedule = Schedule::new().with_system(step);
+        let error = schedule.run_on(&mut set).unwrap_err();
+        assert_eq!(error.error(), &BorrowError::NotFound(Type::of::<Step>()));
+
+        set.insert(Step(3));
+        schedule.run_on(&mut set).unwrap();
+        schedule.run_on(&mut set).unwrap();
+
+        // The values are back in place, in their original order.
+        assert_eq!(set.get_slot(&slot).unwrap().0, 7);
+        assert_eq!(set.get::<[u64; 8]>(), &[0; 8]);
+        assert_eq!(set.len(), 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "is accessed both mutably and immutably")]
+    fn conflicting_parameters_are_rejected() {
+        fn conflict(_: &Counter, _: &mut Counter) {}
+        Schedule::new().add_system(conflict);
+    }
+}
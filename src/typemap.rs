@@ -0,0 +1,214 @@
+use std::{
+    any::Any,
+    fmt::{Debug, Formatter},
+};
+
+use indexmap::IndexMap;
+
+use crate::Type;
+
+/// A key type for a [`TypeMap`], which declares the type of its value.
+///
+/// Key types are usually zero-sized markers, so several keys can share a
+/// value type without wrapping the values in newtypes.
+///
+/// # Example
+///
+/// ```
+/// use singletons::Key;
+///
+/// struct DbUrl;
+///
+/// impl Key for DbUrl {
+///     type Value = String;
+/// }
+/// ```
+pub trait Key: 'static {
+    /// The type of the value stored under this key.
+    type Value: 'static;
+}
+
+/// A map that uses types as keys, where each key type determines the type
+/// of its value.
+///
+/// [`SingletonMap<V>`] stores a single value type `V` for every key. A
+/// `TypeMap` instead looks up the value type through the [`Key`] trait, so
+/// every access is fully type-checked, while values of different types can
+/// live in the same map.
+///
+/// # Example
+///
+/// ```
+/// use singletons::{Key, TypeMap};
+///
+/// struct DbUrl;
+/// struct CacheUrl;
+/// struct MaxConnections;
+///
+/// impl Key for DbUrl {
+///     type Value = String;
+/// }
+/// impl Key for CacheUrl {
+///     type Value = String;
+/// }
+/// impl Key for MaxConnections {
+///     type Value = u32;
+/// }
+///
+/// let mut map = TypeMap::new();
+/// map.insert::<DbUrl>("postgres://".to_string());
+/// map.insert::<CacheUrl>("redis://".to_string());
+/// map.insert::<MaxConnections>(16);
+///
+/// assert_eq!(map.get::<DbUrl>().map(String::as_str), Some("postgres://"));
+/// assert_eq!(map.get::<MaxConnections>(), Some(&16));
+/// ```
+///
+/// [`SingletonMap<V>`]: crate::SingletonMap
+#[derive(Default)]
+pub struct TypeMap(IndexMap<Type, Box<dyn Any>>);
+
+impl TypeMap {
+    /// Creates an empty `TypeMap`.
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        TypeMap(IndexMap::new())
+    }
+
+    /// Creates an empty `TypeMap` with at least the specified capacity.
+    #[inline]
+    #[must_use]
+    pub fn with_capacity(capacity: usize) -> Self {
+        TypeMap(IndexMap::with_capacity(capacity))
+    }
+
+    /// Returns the number of entries in the map.
+    #[inline]
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns true if the map contains no entries.
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Removes all entries from the map.
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    /// Returns true if the map contains an entry for the key type.
+    #[must_use]
+    pub fn contains<K: Key>(&self) -> bool {
+        self.0.contains_key(&Type::of::<K>())
+    }
+
+    /// Inserts a value for the key type, returning the previous value, if
+    /// any.
+    pub fn insert<K: Key>(&mut self, value: K::Value) -> Option<K::Value> {
+        self.0
+            .insert(Type::of::<K>(), Box::new(value))
+            .map(unbox::<K>)
+    }
+
+    /// Returns a reference to the value for the key type, if it exists.
+    #[must_use]
+    pub fn get<K: Key>(&self) -> Option<&K::Value> {
+        self.0.get(&Type::of::<K>()).map(|value| {
+            value
+                .downcast_ref()
+                .expect("values are stored under their key type")
+        })
+    }
+
+    /// Returns a mutable reference to the value for the key type, if it
+    /// exists.
+    pub fn get_mut<K: Key>(&mut self) -> Option<&mut K::Value> {
+        self.0.get_mut(&Type::of::<K>()).map(|value| {
+            value
+                .downcast_mut()
+                .expect("values are stored under their key type")
+        })
+    }
+
+    /// Returns a mutable reference to the value for the key type, inserting
+    /// the result of `default` first if it does not exist.
+    pub fn get_or_insert_with<K: Key>(
+        &mut self,
+        default: impl FnOnce() -> K::Value,
+    ) -> &mut K::Value {
+        self.0
+            .entry(Type::of::<K>())
+            .or_insert_with(|| Box::new(default()))
+            .downcast_mut()
+            .expect("values are stored under their key type")
+    }
+
+    /// Removes the entry for the key type, returning its value if it
+    /// existed.
+    pub fn remove<K: Key>(&mut self) -> Option<K::Value> {
+        self.0.shift_remove(&Type::of::<K>()).map(unbox::<K>)
+    }
+
+    /// Returns an iterator that visits each key [`Type`] in insertion order.
+    pub fn types(&self) -> impl ExactSizeIterator<Item = &Type> + DoubleEndedIterator + '_ {
+        self.0.keys()
+    }
+}
+
+fn unbox<K: Key>(value: Box<dyn Any>) -> K::Value {
+    match value.downcast() {
+        Ok(value) => *value,
+        Err(_) => unreachable!("values are stored under their key type"),
+    }
+}
+
+impl Debug for TypeMap {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.0.keys()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Name;
+    struct Nickname;
+    struct Age;
+
+    impl Key for Name {
+        type Value = String;
+    }
+    impl Key for Nickname {
+        type Value = String;
+    }
+    impl Key for Age {
+        type Value = u8;
+    }
+
+    #[test]
+    fn typemap_keeps_keys_with_the_same_value_type_apart() {
+        let mut map = TypeMap::new();
+        assert_eq!(map.insert::<Name>("Ferris".to_string()), None);
+        map.insert::<Nickname>("Crab".to_string());
+        *map.get_or_insert_with::<Age>(|| 7) += 1;
+
+        assert_eq!(map.get::<Name>().unwrap(), "Ferris");
+        assert_eq!(map.get::<Nickname>().unwrap(), "Crab");
+        assert_eq!(map.get::<Age>(), Some(&8));
+
+        map.get_mut::<Name>().unwrap().push('!');
+        assert_eq!(map.remove::<Name>().as_deref(), Some("Ferris!"));
+        assert!(!map.contains::<Name>());
+        assert_eq!(
+            map.types().copied().collect::<Vec<_>>(),
+            [Type::of::<Nickname>(), Type::of::<Age>()]
+        );
+    }
+}
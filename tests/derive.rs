@@ -0,0 +1,61 @@
+#![cfg(feature = "derive")]
+
+use singletons::{FromSingletons, SingletonSet, Type};
+
+#[derive(Debug, PartialEq)]
+struct Database(&'static str);
+
+#[derive(Clone, Debug, PartialEq)]
+struct Config(u16);
+
+#[derive(Debug, PartialEq)]
+struct Metrics;
+
+#[derive(FromSingletons)]
+struct Deps<'a> {
+    db: &'a Database,
+    config: Config,
+    metrics: Option<&'a Metrics>,
+    port: Option<u16>,
+}
+
+#[derive(FromSingletons)]
+struct Owned(Config, Option<u16>);
+
+#[derive(FromSingletons)]
+struct Nothing;
+
+#[test]
+fn derive_borrows_and_clones_fields() {
+    let mut set = SingletonSet::new();
+    set.insert(Database("postgres://"));
+    set.insert(Config(8080));
+    set.insert(9090u16);
+
+    let deps = Deps::from_set(&set).unwrap();
+    assert_eq!(deps.db, &Database("postgres://"));
+    assert_eq!(deps.config, Config(8080));
+    assert_eq!(deps.metrics, None);
+    assert_eq!(deps.port, Some(9090));
+
+    let Owned(config, port) = Owned::from_set(&set).unwrap();
+    assert_eq!(config, Config(8080));
+    assert_eq!(port, Some(9090));
+}
+
+#[test]
+fn derive_lists_every_missing_type() {
+    let set = SingletonSet::new();
+
+    let error = Deps::from_set(&set).err().unwrap();
+    assert_eq!(
+        error.types(),
+        [Type::of::<Database>(), Type::of::<Config>()]
+    );
+    assert_eq!(
+        error.to_string(),
+        "missing types in SingletonSet: Database, Config"
+    );
+
+    assert!(<Nothing as FromSingletons>::from_set(&set).is_ok());
+}